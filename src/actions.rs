@@ -0,0 +1,481 @@
+use bevy::prelude::*;
+use bevy::input::InputSystem;
+use bevy::input::mouse::{AccumulatedMouseMotion, MouseWheel};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::env;
+use std::path::PathBuf;
+
+pub struct ActionsPlugin;
+
+/// Where bindings are read from, and saved to after rebinding, in the
+/// game's folder under the user's config directory
+const BINDINGS_FILE: &str = "bindings.cfg";
+const CONFIG_DIR: &str = "mardoc";
+/// Sticks closer to the middle than this count as centred
+const DEADZONE: f32 = 0.15;
+/// How far a full right stick turns, in mouse pixels per second
+const PAD_LOOK: f32 = 900.0;
+
+/// Something the player wants to do, whatever they pressed to do it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Use,
+    AltUse,
+    Interact,
+    Drop,
+    NextSlot,
+    PrevSlot,
+    Inventory,
+    SwitchCamera,
+    ToggleCursor,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Self::MoveForward,
+        Self::MoveBack,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Jump,
+        Self::Sprint,
+        Self::Use,
+        Self::AltUse,
+        Self::Interact,
+        Self::Drop,
+        Self::NextSlot,
+        Self::PrevSlot,
+        Self::Inventory,
+        Self::SwitchCamera,
+        Self::ToggleCursor,
+        Self::Pause,
+    ];
+}
+
+/// One thing that can set off an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(GamepadButton),
+    WheelUp,
+    WheelDown,
+}
+
+impl Binding {
+    /// Whether both come from the same kind of input, keys, mouse
+    /// buttons, the wheel or a pad
+    pub fn same_kind(&self, other: &Self) -> bool {
+        use Binding::*;
+        matches!(
+            (self, other),
+            (Key(_), Key(_))
+                | (Mouse(_), Mouse(_))
+                | (Pad(_), Pad(_))
+                | (WheelUp | WheelDown, WheelUp | WheelDown)
+        )
+    }
+
+    pub fn to_config(&self) -> String {
+        match self {
+            Self::Key(k) => format!("Key:{:?}", k),
+            Self::Mouse(b) => format!("Mouse:{:?}", b),
+            Self::Pad(b) => format!("Pad:{:?}", b),
+            Self::WheelUp => "Wheel:Up".to_string(),
+            Self::WheelDown => "Wheel:Down".to_string(),
+        }
+    }
+
+    pub fn from_config(s: &str) -> Option<Self> {
+        let (kind, name) = s.trim().split_once(':')?;
+        match kind {
+            "Key" => by_name(KEYS, name).map(Self::Key),
+            "Mouse" => by_name(MOUSE_BUTTONS, name).map(Self::Mouse),
+            "Pad" => by_name(PAD_BUTTONS, name).map(Self::Pad),
+            "Wheel" if name == "Up" => Some(Self::WheelUp),
+            "Wheel" if name == "Down" => Some(Self::WheelDown),
+            _ => None,
+        }
+    }
+}
+
+fn by_name<T: Debug + Copy>(list: &[T], name: &str) -> Option<T> {
+    list.iter().find(|v| format!("{:?}", v) == name).copied()
+}
+
+/// Keys that can be bound. Anything else can't be written to the
+/// config file, so isn't offered when rebinding.
+pub const KEYS: &[KeyCode] = &[
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE,
+    KeyCode::KeyF, KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ,
+    KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO,
+    KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT,
+    KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Space, KeyCode::Tab, KeyCode::Escape, KeyCode::Enter, KeyCode::Backspace,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::AltLeft, KeyCode::AltRight, KeyCode::CapsLock,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Semicolon, KeyCode::Quote, KeyCode::Slash,
+    KeyCode::Backslash, KeyCode::BracketLeft, KeyCode::BracketRight, KeyCode::Minus,
+    KeyCode::Equal, KeyCode::Backquote, KeyCode::IntlBackslash,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Insert,
+    KeyCode::Delete,
+];
+
+pub const MOUSE_BUTTONS: &[MouseButton] = &[
+    MouseButton::Left,
+    MouseButton::Right,
+    MouseButton::Middle,
+    MouseButton::Back,
+    MouseButton::Forward,
+];
+
+pub const PAD_BUTTONS: &[GamepadButton] = &[
+    GamepadButton::South, GamepadButton::East, GamepadButton::North, GamepadButton::West,
+    GamepadButton::C, GamepadButton::Z,
+    GamepadButton::LeftTrigger, GamepadButton::LeftTrigger2,
+    GamepadButton::RightTrigger, GamepadButton::RightTrigger2,
+    GamepadButton::Select, GamepadButton::Start, GamepadButton::Mode,
+    GamepadButton::LeftThumb, GamepadButton::RightThumb,
+    GamepadButton::DPadUp, GamepadButton::DPadDown, GamepadButton::DPadLeft, GamepadButton::DPadRight,
+];
+
+/// What sets off each action
+#[derive(Resource, Debug, Clone)]
+pub struct Bindings {
+    map: HashMap<Action, Vec<Binding>>,
+    pub path: PathBuf,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        let map = HashMap::from([
+            (Action::MoveForward, vec![Key(KeyCode::KeyW), Pad(GamepadButton::DPadUp)]),
+            (Action::MoveBack, vec![Key(KeyCode::KeyS), Pad(GamepadButton::DPadDown)]),
+            (Action::MoveLeft, vec![Key(KeyCode::KeyA), Pad(GamepadButton::DPadLeft)]),
+            (Action::MoveRight, vec![Key(KeyCode::KeyD), Pad(GamepadButton::DPadRight)]),
+            (Action::Jump, vec![Key(KeyCode::Space), Pad(GamepadButton::South)]),
+            (Action::Sprint, vec![Key(KeyCode::ShiftLeft), Pad(GamepadButton::LeftThumb)]),
+            (Action::Use, vec![Mouse(MouseButton::Left), Pad(GamepadButton::RightTrigger2)]),
+            (Action::AltUse, vec![Mouse(MouseButton::Right), Pad(GamepadButton::LeftTrigger2)]),
+            (Action::Interact, vec![Key(KeyCode::KeyE), Pad(GamepadButton::West)]),
+            (Action::Drop, vec![Key(KeyCode::KeyQ), Pad(GamepadButton::East)]),
+            (Action::NextSlot, vec![WheelDown, Pad(GamepadButton::RightTrigger)]),
+            (Action::PrevSlot, vec![WheelUp, Pad(GamepadButton::LeftTrigger)]),
+            (Action::Inventory, vec![Key(KeyCode::KeyI), Pad(GamepadButton::RightThumb)]),
+            (Action::SwitchCamera, vec![Key(KeyCode::KeyV), Pad(GamepadButton::North)]),
+            (Action::ToggleCursor, vec![Key(KeyCode::Tab), Pad(GamepadButton::Select)]),
+            (Action::Pause, vec![Key(KeyCode::Escape), Pad(GamepadButton::Start)]),
+        ]);
+        Self {
+            map,
+            path: bindings_path(),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.map.get(&action).map_or(&[], |b| b.as_slice())
+    }
+
+    /// Bind `binding` to `action`, in place of whatever it had of the
+    /// same kind. Anything else on that binding loses it.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        for (other, list) in self.map.iter_mut() {
+            if *other != action && list.contains(&binding) {
+                warn!("{:?} was on {:?}, now unbound from it", other, binding);
+                list.retain(|b| *b != binding);
+            }
+        }
+        let list = self.map.entry(action).or_default();
+        list.retain(|b| !b.same_kind(&binding));
+        list.push(binding);
+    }
+
+    /// One `Action = Binding, Binding` per line. Lines starting with
+    /// `#` are comments.
+    pub fn to_config(&self) -> String {
+        let mut out = String::from("# Action = Binding, Binding\n");
+        for action in Action::ALL {
+            let bindings: Vec<String> = self.get(action).iter().map(|b| b.to_config()).collect();
+            out += &format!("{:?} = {}\n", action, bindings.join(", "));
+        }
+        out
+    }
+
+    /// Read bindings over the defaults. Actions missing from the file
+    /// keep their defaults, and anything unreadable is skipped.
+    pub fn from_config(text: &str) -> Self {
+        let mut bindings = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, list)) = line.split_once('=') else {
+                warn!("bad binding line: {}", line);
+                continue;
+            };
+            let Some(action) = by_name(&Action::ALL, name.trim()) else {
+                warn!("no action called {}", name.trim());
+                continue;
+            };
+            let parsed: Vec<Binding> = list
+                .split(',')
+                .filter(|s| !s.trim().is_empty())
+                .filter_map(|s| {
+                    let b = Binding::from_config(s);
+                    if b.is_none() {
+                        warn!("can't bind {}", s.trim());
+                    }
+                    b
+                })
+                .collect();
+            bindings.map.insert(action, parsed);
+        }
+        bindings
+    }
+
+    pub fn load() -> Self {
+        let path = bindings_path();
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                info!("bindings from {:?}", path);
+                Self { path, ..Self::from_config(&text) }
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Some(dir) = self.path.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                warn!("couldn't make {:?}: {}", dir, e);
+            }
+        }
+        match std::fs::write(&self.path, self.to_config()) {
+            Ok(()) => info!("saved bindings to {:?}", self.path),
+            Err(e) => warn!("couldn't save bindings: {}", e),
+        }
+    }
+}
+
+/// The user's config directory for this platform, or beside the game
+/// itself when there isn't one, but never wherever it was run from
+fn bindings_path() -> PathBuf {
+    let config = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    let dir = config
+        .map(|c| c.join(CONFIG_DIR))
+        .or_else(|| env::current_exe().ok()?.parent().map(PathBuf::from))
+        .unwrap_or_default();
+    dir.join(BINDINGS_FILE)
+}
+
+/// This frame's actions
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+    /// Walking direction, `y` forward, no longer than 1
+    pub movement: Vec2,
+    /// Turning this frame, in mouse pixels
+    pub look: Vec2,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+}
+
+/// Screens that take the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Menu {
+    Controls,
+    Inventory,
+}
+
+/// The menu that has the cursor, if any. While one is open only
+/// `Pause`, `Inventory` and `ToggleCursor` get through.
+#[derive(Resource, Default)]
+pub struct MenuOpen(pub Option<Menu>);
+
+impl MenuOpen {
+    pub fn is_open(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+/// The mouse wheel this frame, for wheel bindings
+#[derive(Default)]
+struct Wheel {
+    up: bool,
+    down: bool,
+}
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load());
+        app.init_resource::<ActionState>();
+        app.init_resource::<MenuOpen>();
+        app.add_systems(PreUpdate, read_actions.after(InputSystem));
+    }
+}
+
+fn deadzone(v: Vec2) -> Vec2 {
+    if v.length() < DEADZONE { Vec2::ZERO } else { v }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_actions(
+    time: Res<Time>,
+    bindings: Res<Bindings>,
+    menu: Res<MenuOpen>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut scroll: Local<f32>,
+    gamepads: Query<&Gamepad>,
+    mut state: ResMut<ActionState>,
+) {
+    // Trackpads scroll in pixels, a notch at a time is plenty
+    let mut wheel = Wheel::default();
+    for ev in wheel_events.read() {
+        let notches = match ev.unit {
+            bevy::input::mouse::MouseScrollUnit::Line => ev.y,
+            bevy::input::mouse::MouseScrollUnit::Pixel => {
+                *scroll += ev.y;
+                if scroll.abs() < 50.0 {
+                    continue;
+                }
+                std::mem::take(&mut *scroll)
+            }
+        };
+        wheel.up |= notches > 0.0;
+        wheel.down |= notches < 0.0;
+    }
+
+    let is_down = |b: &Binding| match *b {
+        Binding::Key(k) => keys.pressed(k),
+        Binding::Mouse(m) => buttons.pressed(m),
+        Binding::Pad(p) => gamepads.iter().any(|g| g.pressed(p)),
+        Binding::WheelUp => wheel.up,
+        Binding::WheelDown => wheel.down,
+    };
+
+    let was = std::mem::take(&mut state.pressed);
+    state.just_pressed.clear();
+    state.just_released.clear();
+    for action in Action::ALL {
+        let allowed = !menu.is_open() || matches!(action, Action::Pause | Action::Inventory | Action::ToggleCursor);
+        let list = bindings.get(action);
+        let down = allowed && list.iter().any(is_down);
+        // Every notch is a press, even when the wheel keeps turning
+        let notched = list.iter().any(|b| matches!(b, Binding::WheelUp | Binding::WheelDown) && is_down(b));
+        if down {
+            state.pressed.insert(action);
+            if !was.contains(&action) || notched {
+                state.just_pressed.insert(action);
+            }
+        } else if was.contains(&action) {
+            state.just_released.insert(action);
+        }
+    }
+
+    if menu.is_open() {
+        state.movement = Vec2::ZERO;
+        state.look = Vec2::ZERO;
+        return;
+    }
+
+    let mut movement = Vec2::ZERO;
+    for (action, dir) in [
+        (Action::MoveForward, Vec2::Y),
+        (Action::MoveBack, Vec2::NEG_Y),
+        (Action::MoveLeft, Vec2::NEG_X),
+        (Action::MoveRight, Vec2::X),
+    ] {
+        if state.pressed(action) {
+            movement += dir;
+        }
+    }
+    let stick: Vec2 = gamepads.iter().map(|g| deadzone(g.left_stick())).sum();
+    state.movement = (movement + stick).clamp_length_max(1.0);
+
+    // Stick up looks up, where the mouse has y going down
+    let look_stick: Vec2 = gamepads.iter().map(|g| deadzone(g.right_stick())).sum();
+    state.look = mouse_motion.delta
+        + Vec2::new(look_stick.x, -look_stick.y) * PAD_LOOK * time.delta_secs();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_round_trip_through_config() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Jump, Binding::Mouse(MouseButton::Middle));
+        bindings.rebind(Action::Drop, Binding::WheelUp);
+        let read = Bindings::from_config(&bindings.to_config());
+        for action in Action::ALL {
+            assert_eq!(read.get(action), bindings.get(action), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn rebind_replaces_only_the_same_kind() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Use, Binding::Key(KeyCode::KeyF));
+        assert_eq!(bindings.get(Action::Use), &[
+            Binding::Mouse(MouseButton::Left),
+            Binding::Pad(GamepadButton::RightTrigger2),
+            Binding::Key(KeyCode::KeyF),
+        ]);
+        bindings.rebind(Action::Use, Binding::Key(KeyCode::KeyG));
+        assert!(!bindings.get(Action::Use).contains(&Binding::Key(KeyCode::KeyF)));
+    }
+
+    #[test]
+    fn rebind_takes_the_binding_off_other_actions() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Drop, Binding::Key(KeyCode::KeyE));
+        assert!(!bindings.get(Action::Interact).contains(&Binding::Key(KeyCode::KeyE)));
+        assert!(bindings.get(Action::Drop).contains(&Binding::Key(KeyCode::KeyE)));
+        assert!(!bindings.get(Action::Drop).contains(&Binding::Key(KeyCode::KeyQ)));
+    }
+}
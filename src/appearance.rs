@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+use crate::inventory::BodyPartType;
+use crate::limb::BodyPart;
+
+pub struct AppearancePlugin;
+
+const SKIN_TONES: [&str; 6] = ["#f1c27d", "#e0ac69", "#c68642", "#8d5524", "#ffdbac", "#7a9a5a"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeadModel {
+    Round,
+    Ser,
+}
+
+impl HeadModel {
+    pub fn asset(&self) -> &'static str {
+        match *self {
+            Self::Round => "head.glb",
+            Self::Ser => "serhead.glb",
+        }
+    }
+}
+
+/// How a person looks, all derived from one seed so the same seed
+/// always gives the same person.
+#[derive(Debug, Clone, Component)]
+pub struct Appearance {
+    pub seed: u64,
+    pub skin: Color,
+    pub clothing: Color,
+    pub scale: f32,
+    pub head: HeadModel,
+}
+
+impl Appearance {
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let tone = Srgba::hex(SKIN_TONES[rng.gen_range(0..SKIN_TONES.len())]).unwrap();
+        let skin = Color::from(tone).darker(rng.gen_range(0.0..0.1));
+        let clothing = Color::hsl(
+            rng.gen_range(0.0..360.0),
+            rng.gen_range(0.2..0.7),
+            rng.gen_range(0.25..0.6)
+        );
+
+        Self {
+            seed,
+            skin,
+            clothing,
+            scale: rng.gen_range(0.85..1.15),
+            head: if rng.gen_bool(0.5) { HeadModel::Round } else { HeadModel::Ser },
+        }
+    }
+
+    fn is_skin(part: BodyPartType) -> bool {
+        matches!(part, BodyPartType::Head | BodyPartType::Arm)
+    }
+}
+
+/// Tinted copies of the GLTF materials, one per original per person,
+/// so every part of a person shares them.
+#[derive(Component, Default)]
+pub struct AppearanceMaterials(HashMap<(AssetId<StandardMaterial>, bool), Handle<StandardMaterial>>);
+
+impl Plugin for AppearancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(apply_appearance);
+    }
+}
+
+fn apply_appearance(
+    trigger: Trigger<SceneInstanceReady>,
+    parts: Query<&BodyPart>,
+    parent_q: Query<&Parent>,
+    children: Query<&Children>,
+    mut people: Query<(&Appearance, &mut AppearanceMaterials)>,
+    mut meshes: Query<&mut MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let root = trigger.entity();
+    let Ok(part) = parts.get(root) else {
+        return;
+    };
+    let Some(person) = parent_q.iter_ancestors(root).find(|e| people.contains(*e)) else {
+        return;
+    };
+    let Ok((appearance, mut cache)) = people.get_mut(person) else {
+        return;
+    };
+    let skin = Appearance::is_skin(part.0);
+    let tint = if skin { appearance.skin } else { appearance.clothing };
+
+    // Don't go down into parts nested inside this one, they get their own turn
+    let mut stack: Vec<Entity> = children.get(root).map_or(vec![], |c| c.to_vec());
+    while let Some(e) = stack.pop() {
+        if parts.contains(e) {
+            continue;
+        }
+        if let Ok(c) = children.get(e) {
+            stack.extend(c.iter());
+        }
+        let Ok(mut mat) = meshes.get_mut(e) else {
+            continue;
+        };
+        let key = (mat.0.id(), skin);
+        if let Some(handle) = cache.0.get(&key) {
+            mat.0 = handle.clone();
+            continue;
+        }
+        let Some(mut own) = materials.get(&mat.0).cloned() else {
+            continue;
+        };
+        own.base_color = tint;
+        let handle = materials.add(own);
+        cache.0.insert(key, handle.clone());
+        mat.0 = handle;
+    }
+}
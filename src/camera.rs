@@ -1,15 +1,203 @@
 use std::f32::consts::*;
 
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::actions::{Action, ActionState};
+use crate::crowd::LodViewer;
+use crate::player::Player;
+use crate::terrain::Terrain;
+use crate::tool::ToolViz;
+use crate::town::Building;
 
 pub struct CameraPlugin;
 
+/// Where the eye is, relative to the player
+const EYE: Vec3 = Vec3::new(0.0, 1.5, 0.0);
+/// Where the third person camera wants to be, relative to the eye
+/// and turned with the player
+const SHOULDER: Vec3 = Vec3::new(0.6, 0.4, 4.0);
+/// How far the camera keeps from walls it's been pushed in by
+const WALL_GAP: f32 = 0.3;
+const FLY_SPEED: f32 = 10.0;
+
 #[derive(Component)]
 pub struct MainCamera;
 
+/// Stands in for the player when the camera isn't in their head
+#[derive(Component)]
+struct PlayerBody;
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    FirstPerson,
+    /// Over the shoulder, pulled in by terrain and buildings
+    ThirdPerson,
+    /// Loose from the player, for looking round the town
+    FreeFly,
+}
+
+impl CameraMode {
+    fn next(self) -> Self {
+        match self {
+            Self::FirstPerson => Self::ThirdPerson,
+            Self::ThirdPerson => Self::FreeFly,
+            Self::FreeFly => Self::FirstPerson,
+        }
+    }
+
+    /// Whether input moves and aims the player
+    pub fn controls_player(self) -> bool {
+        self != Self::FreeFly
+    }
+}
+
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        //app.add_systems(Update, move_person);
-        //app.add_observer(spawn_person);
+        app.init_resource::<CameraMode>();
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, (
+            add_player_body,
+            switch_mode,
+            fly_camera,
+            show_player
+        ).chain());
+        app.add_systems(PostUpdate, follow_player.before(TransformSystem::TransformPropagate));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        Name::new("Camera"),
+        Camera3d::default(),
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        Transform::from_translation(EYE),
+        MainCamera,
+        LodViewer
+    ));
+}
+
+fn add_player_body(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    players: Query<Entity, Added<Player>>,
+) {
+    for player in players.iter() {
+        let body = commands.spawn((
+            Name::new("Player body"),
+            PlayerBody,
+            Mesh3d(meshes.add(Capsule3d::new(0.3, 1.2))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Srgba::hex("#446688").unwrap().into(),
+                ..default()
+            })),
+            Transform::from_xyz(0.0, 0.9, 0.0),
+            Visibility::Hidden
+        )).id();
+        commands.entity(player).add_child(body);
+    }
+}
+
+fn switch_mode(
+    actions: Res<ActionState>,
+    mut mode: ResMut<CameraMode>,
+) {
+    if actions.just_pressed(Action::SwitchCamera) {
+        *mode = mode.next();
+        info!("camera {:?}", *mode);
+    }
+}
+
+fn fly_camera(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    mode: Res<CameraMode>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if *mode != CameraMode::FreeFly {
+        return;
+    }
+    let Ok(mut t) = camera.get_single_mut() else {
+        return;
+    };
+
+    let (yaw, pitch, _) = t.rotation.to_euler(EulerRot::YXZ);
+    const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+    let yaw = yaw - actions.look.x * 0.002;
+    let pitch = (pitch - actions.look.y * 0.002).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    t.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+
+    let mut speed = FLY_SPEED;
+    if actions.pressed(Action::Sprint) {
+        speed *= 5.0;
+    }
+    let mut mo = *t.forward() * actions.movement.y + *t.right() * actions.movement.x;
+    if actions.pressed(Action::Jump) {
+        mo += Vec3::Y;
+    }
+    t.translation += mo * speed * time.delta_secs();
+}
+
+/// The body shows, and the viewmodel hides, once the camera leaves the
+/// player's head
+fn show_player(
+    mode: Res<CameraMode>,
+    mut bodies: Query<&mut Visibility, (With<PlayerBody>, Without<ToolViz>)>,
+    mut viewmodels: Query<&mut Visibility, (With<ToolViz>, Without<PlayerBody>)>,
+) {
+    let first_person = *mode == CameraMode::FirstPerson;
+    for mut v in bodies.iter_mut() {
+        v.set_if_neq(if first_person { Visibility::Hidden } else { Visibility::Inherited });
+    }
+    for mut v in viewmodels.iter_mut() {
+        v.set_if_neq(if first_person { Visibility::Inherited } else { Visibility::Hidden });
+    }
+}
+
+fn follow_player(
+    mode: Res<CameraMode>,
+    mut ray_cast: MeshRayCast,
+    player: Query<&Transform, (With<Player>, Without<MainCamera>)>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    terrain: Query<(), With<Terrain>>,
+    buildings: Query<(), With<Building>>,
+    parent_q: Query<&Parent>,
+) {
+    let (Ok(player), Ok(mut t)) = (player.get_single(), camera.get_single_mut()) else {
+        return;
+    };
+
+    match *mode {
+        CameraMode::FirstPerson => {
+            *t = player.mul_transform(Transform::from_translation(EYE));
+        }
+        CameraMode::ThirdPerson => {
+            let eye = player.translation + EYE;
+            let want = player.rotation * SHOULDER;
+            let mut distance = want.length();
+            let filter = |entity| {
+                terrain.contains(entity)
+                    || parent_q.iter_ancestors(entity).any(|a| buildings.contains(a))
+            };
+            // Walls behind the player are usually out of view
+            let settings = RayCastSettings::default()
+                .with_filter(&filter)
+                .with_visibility(RayCastVisibility::Any);
+            if let Ok(dir) = Dir3::new(want) {
+                if let Some((_, hit)) = ray_cast.cast_ray(Ray3d::new(eye, dir), &settings).first() {
+                    distance = distance.min((hit.distance - WALL_GAP).max(0.0));
+                }
+            }
+            t.translation = eye + want.normalize_or_zero() * distance;
+            t.rotation = player.rotation;
+        }
+        CameraMode::FreeFly => {}
     }
 }
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use crate::actions::{Action, ActionState};
+use crate::camera::CameraMode;
+use crate::interact::{InteractLayers, Interactables};
+use crate::limb::Capabilities;
+use crate::person::{Carryable, Carried, Knockback};
+use crate::physics::{impulse, Mass};
+use crate::player::{Player, RaycastTarget};
+use crate::ragdoll::{RagdollBody, Settled};
+use crate::vitals::Dead;
+
+pub struct CarryPlugin;
+
+/// Where held things sit, relative to the player
+const HOLD: Vec3 = Vec3::new(0.0, 1.2, -2.5);
+/// Seconds of holding the throw button to reach full power
+const MAX_CHARGE: f32 = 1.5;
+const MAX_THROW: f32 = 120.0;
+/// Furthest away something can be picked up from
+pub const PICKUP_REACH: f32 = 3.0;
+/// Share of the most the player can carry that halves their speed
+const HALF_SPEED_LOAD: f32 = 1.0 / 3.0;
+
+/// What the player has in their arms
+#[derive(Component, Default)]
+pub struct Carrying {
+    pub entity: Option<Entity>,
+    /// How long the throw has been charging for
+    pub charge: f32,
+}
+
+impl Carrying {
+    /// Walking speed multiplier for carrying something this heavy, with
+    /// arms that can carry up to `capacity`
+    pub fn speed(mass: f32, capacity: f32) -> f32 {
+        1.0 / (1.0 + mass / (capacity * HALF_SPEED_LOAD).max(0.01))
+    }
+}
+
+/// Pick something up
+#[derive(Debug, Event)]
+pub struct CarryStuff {
+    pub entity: Entity
+}
+
+/// Let go of whatever is carried, throwing it with `power`
+#[derive(Debug, Event)]
+pub struct DropCarried {
+    pub power: f32
+}
+
+impl Plugin for CarryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            carry_input,
+            forget_missing
+        ));
+        app.add_observer(carry_stuff);
+        app.add_observer(drop_carried);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn carry_input(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    camera: Res<CameraMode>,
+    ray_target: Res<RaycastTarget>,
+    mut interactables: Interactables,
+    mut player: Query<&mut Carrying, (With<Player>, Without<Dead>)>,
+    parent_q: Query<&Parent>,
+    carryable: Query<(), (With<Carryable>, Without<Carried>)>,
+    mut commands: Commands,
+) {
+    let Ok(mut carrying) = player.get_single_mut() else {
+        return;
+    };
+    if !camera.controls_player() {
+        return;
+    }
+
+    if carrying.entity.is_none() {
+        carrying.charge = 0.0;
+        if !actions.just_pressed(Action::Interact) {
+            return;
+        }
+        // Whatever tool is in hand, hands can grab what it can't
+        let ray = Ray3d::new(ray_target.origin, ray_target.dir);
+        let layers = InteractLayers::PERSON | InteractLayers::BODY_PART | InteractLayers::PROP;
+        let Some(hit) = interactables.nearest(ray, PICKUP_REACH, layers) else {
+            return;
+        };
+        let root = parent_q.root_ancestor(hit.mesh);
+        if carryable.contains(root) {
+            commands.trigger(CarryStuff { entity: root });
+        }
+        return;
+    }
+
+    if actions.just_pressed(Action::Drop) {
+        commands.trigger(DropCarried { power: 0.0 });
+        return;
+    }
+
+    // Hold to wind up, let go to throw
+    if actions.pressed(Action::AltUse) {
+        carrying.charge = (carrying.charge + time.delta_secs()).min(MAX_CHARGE);
+    }
+    if actions.just_released(Action::AltUse) {
+        let power = carrying.charge / MAX_CHARGE * MAX_THROW;
+        commands.trigger(DropCarried { power });
+    }
+}
+
+fn carry_stuff(
+    trigger: Trigger<CarryStuff>,
+    mut player_query: Query<(Entity, &mut Carrying, &Capabilities), With<Player>>,
+    mut things: Query<(&mut Transform, Option<&Mass>), With<Carryable>>,
+    mut commands: Commands,
+) {
+    let Ok((player, mut carrying, caps)) = player_query.get_single_mut() else {
+        return;
+    };
+    if carrying.entity.is_some() {
+        return;
+    }
+
+    let thing = trigger.event().entity;
+    let Ok((mut t, mass)) = things.get_mut(thing) else {
+        info!("can't carry that");
+        return;
+    };
+    if !caps.can_carry || mass.is_some_and(|m| m.0 > caps.carry_capacity) {
+        info!("too heavy to carry");
+        return;
+    }
+
+    info!("carrying {:?}", thing);
+    carrying.entity = Some(thing);
+    carrying.charge = 0.0;
+    t.translation = HOLD;
+    commands
+        .entity(thing)
+        .remove::<(Knockback, Settled)>()
+        .insert(Carried)
+        .set_parent(player);
+}
+
+fn drop_carried(
+    trigger: Trigger<DropCarried>,
+    mut player_query: Query<(&mut Carrying, &GlobalTransform), With<Player>>,
+    mut things: Query<(Option<&Mass>, Option<&mut RagdollBody>)>,
+    mut commands: Commands,
+) {
+    let Ok((mut carrying, player_t)) = player_query.get_single_mut() else {
+        return;
+    };
+    let Some(thing) = carrying.entity.take() else {
+        return;
+    };
+    carrying.charge = 0.0;
+
+    let Ok((mass, ragdoll)) = things.get_mut(thing) else {
+        return;
+    };
+    let power = trigger.event().power;
+    let velocity = impulse(power, *player_t.forward(), mass);
+    info!("threw {:?} at {:?}", thing, velocity.length());
+
+    // Bodies fall with the ragdoll, anything else just flies
+    commands.entity(thing).remove::<Carried>().remove_parent_in_place();
+    match ragdoll {
+        Some(mut body) => {
+            body.velocity = velocity;
+            commands.entity(thing).remove::<Settled>();
+        }
+        None => {
+            commands.entity(thing).insert(Knockback { velocity, grounded: false });
+        }
+    }
+}
+
+/// Things can rot away or get cut up while held
+fn forget_missing(
+    mut player: Query<&mut Carrying, With<Player>>,
+    carried: Query<(), With<Carried>>,
+) {
+    for mut carrying in player.iter_mut() {
+        if carrying.entity.is_some_and(|e| !carried.contains(e)) {
+            carrying.entity = None;
+        }
+    }
+}
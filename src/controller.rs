@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+
+use crate::physics::{FRICTION, GRAVITY};
+use crate::terrain::{height_at, Terrain};
+
+pub struct ControllerPlugin;
+
+/// Moves a body over the ground: walks where it's told, falls when
+/// there's nothing under it, and climbs small steps.
+#[derive(Debug, Component)]
+pub struct CharacterController {
+    /// Horizontal velocity it wants to walk at
+    pub wish: Vec3,
+    pub velocity: Vec3,
+    pub jump_speed: f32,
+    /// Steepest slope, in radians, that can be walked up
+    pub max_slope: f32,
+    /// Tallest ledge that can be walked up without jumping
+    pub step_height: f32,
+    /// Horizontal velocity picked up sliding down something too steep,
+    /// on top of `wish`
+    slide: Vec3,
+    jump: bool,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            wish: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            jump_speed: 5.0,
+            max_slope: 50f32.to_radians(),
+            step_height: 0.4,
+            slide: Vec3::ZERO,
+            jump: false,
+        }
+    }
+}
+
+impl CharacterController {
+    /// Jump next update, if standing on something
+    pub fn jump(&mut self) {
+        self.jump = true;
+    }
+
+    /// Stop dead, sliding included
+    pub fn stop(&mut self) {
+        self.velocity = Vec3::ZERO;
+        self.slide = Vec3::ZERO;
+    }
+}
+
+/// Standing on the ground
+#[derive(Component)]
+pub struct Grounded;
+
+/// Hit the ground after being in the air
+#[derive(Debug, Event)]
+pub struct Landed {
+    /// How fast it was falling, in m/s
+    pub speed: f32
+}
+
+impl Plugin for ControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, move_controllers);
+    }
+}
+
+/// Ground height and normal under `pos`, looking down from `top`.
+/// Falls back to the terrain function when no ground mesh is hit,
+/// like before the town has loaded.
+pub fn ground_below(ray_cast: &mut MeshRayCast, terrain: &Query<(), With<Terrain>>, pos: Vec3, top: f32) -> (f32, Vec3) {
+    let filter = |entity| terrain.contains(entity);
+    // The ground under our feet is often out of view
+    let settings = RayCastSettings::default()
+        .with_filter(&filter)
+        .with_visibility(RayCastVisibility::Any);
+    let ray = Ray3d::new(Vec3::new(pos.x, top, pos.z), Dir3::NEG_Y);
+    match ray_cast.cast_ray(ray, &settings).first() {
+        Some((_, hit)) => (hit.point.y, hit.normal),
+        None => (height_at(pos.x, pos.z), Vec3::Y),
+    }
+}
+
+fn move_controllers(
+    time: Res<Time>,
+    mut ray_cast: MeshRayCast,
+    terrain: Query<(), With<Terrain>>,
+    mut q: Query<(Entity, &mut Transform, &mut CharacterController, Has<Grounded>)>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_secs();
+    for (e, mut t, mut controller, was_grounded) in q.iter_mut() {
+        let pos = t.translation;
+        let step = controller.step_height;
+
+        controller.velocity.x = controller.wish.x + controller.slide.x;
+        controller.velocity.z = controller.wish.z + controller.slide.z;
+        if was_grounded && controller.jump {
+            controller.velocity.y = controller.jump_speed;
+        } else if !was_grounded {
+            controller.velocity.y -= GRAVITY * dt;
+        }
+        controller.jump = false;
+
+        let mut next = pos + controller.velocity * dt;
+        let (mut ground, mut normal) = ground_below(&mut ray_cast, &terrain, next, pos.y + step);
+
+        // Too tall to step onto, or too steep to walk up: stay put sideways
+        let walkable = normal.y >= controller.max_slope.cos();
+        let climbing = ground > pos.y;
+        if ground > pos.y + step || (climbing && !walkable) {
+            next.x = pos.x;
+            next.z = pos.z;
+            (ground, normal) = ground_below(&mut ray_cast, &terrain, next, pos.y + step);
+        }
+
+        // Stick to the ground going down slopes and steps, rather than
+        // bouncing off them
+        let falling = controller.velocity.y <= 0.0;
+        let snap = if was_grounded { step } else { 0.0 };
+        let grounded = falling && next.y <= ground + snap && normal.y >= controller.max_slope.cos();
+
+        let impact = -controller.velocity.y;
+        if grounded {
+            next.y = ground;
+            controller.velocity.y = 0.0;
+            // Sliding runs out once back on something walkable
+            controller.slide *= (1.0 - FRICTION * dt).max(0.0);
+        } else if next.y < ground {
+            // Sliding down something too steep to stand on speeds up
+            // from next update, like falling does
+            controller.slide += Vec3::new(normal.x, 0.0, normal.z) * GRAVITY * dt;
+            next.y = ground;
+            controller.velocity.y = controller.velocity.y.max(0.0);
+        }
+        t.translation = next;
+
+        if grounded && !was_grounded {
+            commands.entity(e).insert(Grounded);
+            commands.trigger_targets(Landed { speed: impact }, e);
+        } else if !grounded && was_grounded {
+            commands.entity(e).remove::<Grounded>();
+        }
+    }
+}
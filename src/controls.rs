@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use bevy::input::mouse::MouseWheel;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+use crate::actions::{Action, ActionState, Binding, Bindings, Menu, MenuOpen, KEYS};
+
+pub struct ControlsPlugin;
+
+/// The pause screen, listing every action and what it's bound to
+#[derive(Component)]
+struct ControlsMenu;
+
+/// Click to rebind this action
+#[derive(Component)]
+struct BindingRow(Action);
+
+#[derive(Component)]
+struct QuitButton;
+
+/// Waiting for a press to bind to `action`. Not `armed` until the
+/// frame after the click, so the click itself isn't bound.
+#[derive(Resource, Default)]
+struct Capture(Option<(Action, bool)>);
+
+const ROW: &str = "#333333";
+const ROW_HOVER: &str = "#555555";
+const ROW_CAPTURE: &str = "#884400";
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Capture>();
+        app.add_systems(Update, (
+            toggle_menu,
+            click_buttons,
+            capture_binding,
+            show_bindings,
+            release_cursor
+        ).chain());
+    }
+}
+
+fn toggle_menu(
+    actions: Res<ActionState>,
+    capture: Res<Capture>,
+    mut menu: ResMut<MenuOpen>,
+    menus: Query<Entity, With<ControlsMenu>>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::Pause) || capture.0.is_some() {
+        return;
+    }
+    // Pause shuts whatever menu is up, and only opens this one if none was
+    if menu.is_open() {
+        menu.0 = None;
+        for e in menus.iter() {
+            commands.entity(e).despawn_recursive();
+        }
+        return;
+    }
+    menu.0 = Some(Menu::Controls);
+
+    commands.spawn((
+        Name::new("Controls"),
+        ControlsMenu,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+    )).with_children(|p| {
+        p.spawn(Text::new("Controls - click one, then press what to bind it to"));
+        for action in Action::ALL {
+            p.spawn((
+                Button,
+                BindingRow(action),
+                Node {
+                    width: Val::Px(480.0),
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                    ..default()
+                },
+                BackgroundColor(Srgba::hex(ROW).unwrap().into()),
+            )).with_child(Text::new(""));
+        }
+        p.spawn((
+            Button,
+            QuitButton,
+            Node {
+                margin: UiRect::top(Val::Px(16.0)),
+                padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                ..default()
+            },
+            BackgroundColor(Srgba::hex(ROW).unwrap().into()),
+        )).with_child(Text::new("Quit"));
+    });
+}
+
+fn click_buttons(
+    mut buttons: Query<(&Interaction, Option<&BindingRow>, Has<QuitButton>, &mut BackgroundColor), Changed<Interaction>>,
+    mut capture: ResMut<Capture>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, row, quit, mut bg) in buttons.iter_mut() {
+        let capturing = row.is_some_and(|r| capture.0.is_some_and(|(a, _)| a == r.0));
+        match *interaction {
+            Interaction::Pressed => {
+                if quit {
+                    exit.send(AppExit::Success);
+                }
+                if let Some(row) = row {
+                    capture.0 = Some((row.0, false));
+                }
+            }
+            Interaction::Hovered if !capturing => {
+                bg.0 = Srgba::hex(ROW_HOVER).unwrap().into();
+            }
+            Interaction::None if !capturing => {
+                bg.0 = Srgba::hex(ROW).unwrap().into();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Bind the next key, button or wheel turn to the action being rebound.
+/// Escape gives up.
+fn capture_binding(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    gamepads: Query<&Gamepad>,
+    mut capture: ResMut<Capture>,
+    mut bindings: ResMut<Bindings>,
+) {
+    let scrolled = wheel.read().map(|ev| ev.y).sum::<f32>();
+    let Some((action, armed)) = capture.0.as_mut() else {
+        return;
+    };
+    if !*armed {
+        *armed = true;
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        capture.0 = None;
+        return;
+    }
+
+    let pressed = keys
+        .get_just_pressed()
+        .find(|k| KEYS.contains(k))
+        .map(|k| Binding::Key(*k))
+        .or_else(|| buttons.get_just_pressed().next().map(|b| Binding::Mouse(*b)))
+        .or_else(|| {
+            gamepads
+                .iter()
+                .find_map(|g| g.get_just_pressed().next().copied())
+                .map(Binding::Pad)
+        })
+        .or((scrolled > 0.0).then_some(Binding::WheelUp))
+        .or((scrolled < 0.0).then_some(Binding::WheelDown));
+    let Some(binding) = pressed else {
+        return;
+    };
+
+    info!("{:?} now on {:?}", action, binding);
+    bindings.rebind(*action, binding);
+    bindings.save();
+    capture.0 = None;
+}
+
+fn show_bindings(
+    bindings: Res<Bindings>,
+    capture: Res<Capture>,
+    added: Query<(), Added<BindingRow>>,
+    mut rows: Query<(&BindingRow, &Children, &mut BackgroundColor)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !bindings.is_changed() && !capture.is_changed() && added.is_empty() {
+        return;
+    }
+    for (row, children, mut bg) in rows.iter_mut() {
+        let capturing = capture.0.is_some_and(|(a, _)| a == row.0);
+        let Some(mut text) = children.first().and_then(|c| texts.get_mut(*c).ok()) else {
+            continue;
+        };
+        **text = if capturing {
+            format!("{:?}: press something...", row.0)
+        } else {
+            let list: Vec<String> = bindings.get(row.0).iter().map(|b| b.to_config()).collect();
+            format!("{:?}: {}", row.0, list.join(", "))
+        };
+        bg.0 = Srgba::hex(if capturing { ROW_CAPTURE } else { ROW }).unwrap().into();
+    }
+}
+
+/// The cursor is free while any menu is up, and locked again after
+fn release_cursor(
+    menu: Res<MenuOpen>,
+    mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !menu.is_changed() || menu.is_added() {
+        return;
+    }
+    let Ok(mut primary_window) = q_windows.get_single_mut() else {
+        return;
+    };
+    if menu.is_open() {
+        primary_window.cursor_options.grab_mode = CursorGrabMode::None;
+        primary_window.cursor_options.visible = true;
+    } else {
+        primary_window.cursor_options.grab_mode = CursorGrabMode::Locked;
+        primary_window.cursor_options.visible = false;
+    }
+}
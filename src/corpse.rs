@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use bevy::color::Mix;
+
+use crate::crowd::{CrowdSet, SimBudget};
+use crate::interact::Highlighted;
+use crate::limb::Capabilities;
+use crate::person::{Carried, Person};
+use crate::status::{StatusEffects, StatusKind};
+use crate::townsfolk::{Hostile, TownsfolkTask, TownsfolkTaskType};
+
+pub struct CorpsePlugin;
+
+/// Colour everything ends up as
+const ROT: Color = Color::srgb(0.25, 0.3, 0.15);
+/// How often decaying materials get re-tinted
+const TINT_EVERY: f32 = 0.5;
+/// How close to a grave a corpse has to be left to get buried
+const GRAVE_RADIUS: f32 = 2.0;
+/// Last part of the decay is spent sinking into the ground
+const SINK_FROM: f32 = 0.8;
+const SINK_DEPTH: f32 = 1.0;
+
+#[derive(Resource)]
+pub struct CorpseConfig {
+    /// Game seconds from death to gone
+    pub decay_secs: f32,
+    /// Oldest corpses are cleared out past this many
+    pub max_corpses: usize,
+}
+
+impl Default for CorpseConfig {
+    fn default() -> Self {
+        Self {
+            decay_secs: 300.0,
+            max_corpses: 30
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Corpse {
+    pub age: f32,
+    since_tint: f32,
+    /// Our own copies of the body's materials, and what colour they started as
+    tints: Vec<(Handle<StandardMaterial>, Color)>,
+    sunk: f32,
+}
+
+impl Corpse {
+    fn decay(&self, config: &CorpseConfig) -> f32 {
+        (self.age / config.decay_secs).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Grave {
+    pub occupants: u32,
+}
+
+#[derive(Debug, Event)]
+pub struct BuryCorpse {
+    pub grave: Entity
+}
+
+impl Plugin for CorpsePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CorpseConfig>();
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, (
+            own_materials,
+            decay_corpses,
+            cap_corpses,
+            find_graves,
+            react_to_corpses.after(CrowdSet)
+        ));
+        app.add_observer(bury_corpse);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    // A little graveyard out the back of town
+    for i in 0..4 {
+        commands.spawn((
+            Name::new("grave"),
+            Grave::default(),
+            SceneRoot(
+                asset_server
+                    .load(GltfAssetLabel::Scene(0).from_asset("plinth.glb"))),
+            Transform::from_xyz(-30.0 + i as f32 * 3.0, 0.0, 30.0)
+        ));
+    }
+}
+
+/// Give each new corpse its own materials so it can rot on its own.
+/// A highlighted mesh gets its copy once it's no longer aimed at.
+fn own_materials(
+    mut corpses: Query<(Entity, &mut Corpse), Added<Corpse>>,
+    children: Query<&Children>,
+    mut meshes: Query<(&mut MeshMaterial3d<StandardMaterial>, Option<&mut Highlighted>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (e, mut corpse) in corpses.iter_mut() {
+        for c in children.iter_descendants(e) {
+            let Ok((mut mat, highlighted)) = meshes.get_mut(c) else {
+                continue;
+            };
+            let shared = highlighted.as_ref().map_or(&mat.0, |h| &h.original);
+            let Some(own) = materials.get(shared).cloned() else {
+                continue;
+            };
+            let color = own.base_color;
+            let handle = materials.add(own);
+            match highlighted {
+                Some(mut h) => h.original = handle.clone(),
+                None => mat.0 = handle.clone(),
+            }
+            corpse.tints.push((handle, color));
+        }
+    }
+}
+
+fn decay_corpses(
+    time: Res<Time>,
+    config: Res<CorpseConfig>,
+    mut corpses: Query<(Entity, &mut Corpse, &mut Transform, Has<Carried>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_secs();
+    for (e, mut corpse, mut t, carried) in corpses.iter_mut() {
+        corpse.age += dt;
+        let decay = corpse.decay(&config);
+
+        if decay >= 1.0 {
+            info!("corpse rotted away");
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+
+        corpse.since_tint += dt;
+        if corpse.since_tint >= TINT_EVERY {
+            corpse.since_tint = 0.0;
+            for (handle, color) in corpse.tints.iter() {
+                if let Some(mat) = materials.get_mut(handle) {
+                    mat.base_color = color.mix(&ROT, decay);
+                }
+            }
+        }
+
+        // Don't sink out of the player's arms
+        if !carried && decay > SINK_FROM {
+            let sink = (decay - SINK_FROM) / (1.0 - SINK_FROM) * SINK_DEPTH;
+            t.translation.y -= sink - corpse.sunk;
+            corpse.sunk = sink;
+        }
+    }
+}
+
+fn cap_corpses(
+    config: Res<CorpseConfig>,
+    corpses: Query<(Entity, &Corpse)>,
+    mut commands: Commands,
+) {
+    let count = corpses.iter().count();
+    if count <= config.max_corpses {
+        return;
+    }
+    let mut by_age: Vec<(Entity, f32)> = corpses.iter().map(|(e, c)| (e, c.age)).collect();
+    by_age.sort_by(|a, b| b.1.total_cmp(&a.1));
+    for (e, _) in by_age.into_iter().take(count - config.max_corpses) {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+/// Corpses left lying next to a grave go in it
+fn find_graves(
+    corpses: Query<(Entity, &GlobalTransform), (With<Corpse>, Without<Carried>)>,
+    graves: Query<(Entity, &GlobalTransform), With<Grave>>,
+    mut commands: Commands,
+) {
+    for (corpse, ct) in corpses.iter() {
+        let near = graves
+            .iter()
+            .find(|(_, gt)| gt.translation().distance(ct.translation()) < GRAVE_RADIUS);
+        if let Some((grave, _)) = near {
+            commands.trigger_targets(BuryCorpse { grave }, corpse);
+        }
+    }
+}
+
+fn bury_corpse(
+    trigger: Trigger<BuryCorpse>,
+    mut graves: Query<&mut Grave>,
+    corpses: Query<(), With<Corpse>>,
+    mut commands: Commands,
+) {
+    let corpse = trigger.entity();
+    if !corpses.contains(corpse) {
+        return;
+    }
+    let Ok(mut grave) = graves.get_mut(trigger.event().grave) else {
+        return;
+    };
+    grave.occupants += 1;
+    info!("buried, {} in this grave", grave.occupants);
+    commands.entity(corpse).despawn_recursive();
+}
+
+/// Townsfolk who see a body run away from it, unless they're too angry
+fn react_to_corpses(
+    corpses: Query<&GlobalTransform, With<Corpse>>,
+    mut folk: Query<(&mut Transform, &GlobalTransform, &Capabilities, &mut TownsfolkTask, Option<&SimBudget>, Option<&StatusEffects>), (With<Person>, Without<Hostile>)>,
+) {
+    for (mut t, gt, caps, mut task, budget, effects) in folk.iter_mut() {
+        if budget.is_some_and(|b| !b.ready) {
+            continue;
+        }
+        // Too busy being on fire or in love to care
+        if effects.is_some_and(|e| e.has(StatusKind::Burning) || e.has(StatusKind::Charmed)) {
+            continue;
+        }
+        let pos = gt.translation();
+        let nearest = corpses
+            .iter()
+            .map(|c| c.translation())
+            .filter(|c| c.distance(pos) < caps.perception)
+            .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)));
+
+        match (nearest, &task.task) {
+            (Some(corpse), _) => {
+                let away = ((pos - corpse) * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero();
+                if away != Vec3::ZERO {
+                    t.look_to(away, Vec3::Y);
+                }
+                if !matches!(task.task, TownsfolkTaskType::Fleaing) {
+                    info!("Eek, a body!");
+                    task.task = TownsfolkTaskType::Fleaing;
+                }
+            }
+            (None, TownsfolkTaskType::Fleaing) => {
+                task.task = TownsfolkTaskType::Idle;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use bevy::prelude::*;
+use bevy::pbr::NotShadowCaster;
+
+use crate::appearance::Appearance;
+use crate::person::GltfBodyPart;
+
+/// Level of detail for the crowd. `CrowdSimPlugin` is the part that
+/// runs headless, `CrowdPlugin` adds the rendering side on top.
+pub struct CrowdPlugin;
+pub struct CrowdSimPlugin;
+
+/// Whoever the crowd is being drawn for
+#[derive(Component)]
+pub struct LodViewer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Default)]
+pub enum LodLevel {
+    /// Full body part hierarchy, simulated every frame
+    #[default]
+    Near,
+    /// Full hierarchy, thinks less often
+    Mid,
+    /// One merged stand-in mesh, thinks rarely
+    Far,
+}
+
+#[derive(Resource)]
+pub struct CrowdConfig {
+    pub near: f32,
+    pub far: f32,
+    /// Seconds between simulation updates at each level
+    pub mid_interval: f32,
+    pub far_interval: f32,
+}
+
+impl Default for CrowdConfig {
+    fn default() -> Self {
+        Self {
+            near: 25.0,
+            far: 60.0,
+            mid_interval: 0.1,
+            far_interval: 0.5,
+        }
+    }
+}
+
+/// Lets far away people update less often. When `ready`, `dt` is
+/// all the time that has built up since the last update.
+#[derive(Component, Default)]
+pub struct SimBudget {
+    accum: f32,
+    pub ready: bool,
+    pub dt: f32,
+}
+
+#[derive(Component)]
+struct Impostor;
+
+#[derive(Resource)]
+struct ImpostorAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrowdSet;
+
+impl Plugin for CrowdSimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CrowdConfig>();
+        app.add_systems(Update, (
+            classify_lod,
+            tick_budgets
+        ).chain().in_set(CrowdSet));
+    }
+}
+
+impl Plugin for CrowdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(CrowdSimPlugin);
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, swap_representation.after(CrowdSet));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ImpostorAssets {
+        mesh: meshes.add(Capsule3d::new(0.2, 1.1)),
+        material: materials.add(StandardMaterial {
+            base_color: Srgba::hex("#887766").unwrap().into(),
+            perceptual_roughness: 0.9,
+            ..default()
+        }),
+    });
+}
+
+fn classify_lod(
+    config: Res<CrowdConfig>,
+    viewer: Query<&GlobalTransform, With<LodViewer>>,
+    mut people: Query<(&GlobalTransform, &mut LodLevel)>,
+) {
+    let Ok(viewer) = viewer.get_single() else {
+        return;
+    };
+    let eye = viewer.translation();
+    for (t, mut lod) in people.iter_mut() {
+        let d = t.translation().distance(eye);
+        let next = if d < config.near {
+            LodLevel::Near
+        } else if d < config.far {
+            LodLevel::Mid
+        } else {
+            LodLevel::Far
+        };
+        lod.set_if_neq(next);
+    }
+}
+
+fn tick_budgets(
+    time: Res<Time>,
+    config: Res<CrowdConfig>,
+    mut q: Query<(&LodLevel, &mut SimBudget)>,
+) {
+    let dt = time.delta_secs();
+    for (lod, mut budget) in q.iter_mut() {
+        let interval = match lod {
+            LodLevel::Near => 0.0,
+            LodLevel::Mid => config.mid_interval,
+            LodLevel::Far => config.far_interval,
+        };
+        budget.accum += dt;
+        budget.ready = budget.accum >= interval;
+        if budget.ready {
+            budget.dt = budget.accum;
+            budget.accum = 0.0;
+        }
+    }
+}
+
+/// Far away, hide the body parts and show one capsule instead
+fn swap_representation(
+    assets: Res<ImpostorAssets>,
+    mut people: Query<(Entity, &LodLevel, Option<&Appearance>, Option<&mut AnimationPlayer>), Changed<LodLevel>>,
+    children: Query<&Children>,
+    mut parts: Query<&mut Visibility, (With<GltfBodyPart>, Without<Impostor>)>,
+    mut impostors: Query<&mut Visibility, With<Impostor>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for (e, lod, appearance, player) in people.iter_mut() {
+        let far = *lod == LodLevel::Far;
+        let mut has_impostor = false;
+
+        for c in children.get(e).map_or(&[][..], |c| &c[..]) {
+            if let Ok(mut vis) = parts.get_mut(*c) {
+                *vis = if far { Visibility::Hidden } else { Visibility::Inherited };
+            }
+            if let Ok(mut vis) = impostors.get_mut(*c) {
+                has_impostor = true;
+                *vis = if far { Visibility::Inherited } else { Visibility::Hidden };
+            }
+        }
+
+        if far && !has_impostor {
+            // Tinted like their clothes so the crowd still looks mixed from afar
+            let material = match appearance {
+                Some(a) => materials.add(StandardMaterial {
+                    base_color: a.clothing,
+                    perceptual_roughness: 0.9,
+                    ..default()
+                }),
+                None => assets.material.clone(),
+            };
+            let impostor = commands.spawn((
+                Name::new("Impostor"),
+                Impostor,
+                Mesh3d(assets.mesh.clone()),
+                MeshMaterial3d(material),
+                Transform::from_xyz(0.0, 0.75, 0.0),
+                NotShadowCaster,
+            )).id();
+            commands.entity(e).add_child(impostor);
+        }
+
+        if let Some(mut player) = player {
+            if far {
+                player.pause_all();
+            } else {
+                player.resume_all();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use crate::inventory::BodyPartType;
+    use crate::limb::{BodyPart, Capabilities, LimbPlugin, LimbHealth};
+    use crate::person::{BodyRoot, Health, Person, PersonPlugin, Speed};
+    use crate::status::StatusPlugin;
+    use crate::townsfolk::{TownsfolkTask, TownsfolkTaskType};
+
+    /// Headless run of the crowd simulation only, at growing sizes, to
+    /// see how many people it can think for in a 60 fps frame. Bare
+    /// people with no scenes, so no rendering, animation, IK, appearance
+    /// or impostors are in the timings.
+    /// `cargo test --release crowd_sim_bench -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn crowd_sim_bench() {
+        const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+        const FRAMES: u32 = 120;
+
+        println!("{:>8} {:>10} {:>8}", "people", "ms/frame", "60fps");
+        let mut n = 250;
+        loop {
+            let mut app = App::new();
+            app.add_plugins((MinimalPlugins, HierarchyPlugin, TransformPlugin, AssetPlugin::default()));
+            // Ground is found by ray casts, which hit nothing here
+            app.init_asset::<Mesh>();
+            app.add_plugins((PersonPlugin, LimbPlugin, StatusPlugin, CrowdSimPlugin));
+            app.finish();
+            app.cleanup();
+
+            app.world_mut().spawn((LodViewer, Transform::IDENTITY, GlobalTransform::IDENTITY));
+            let half = 500.0;
+            for i in 0..n as u64 {
+                let x = ((i * 7919) % 1000) as f32 - half;
+                let z = ((i * 104729) % 1000) as f32 - half;
+                app.world_mut().spawn((
+                    Person,
+                    BodyRoot,
+                    Health(100.0),
+                    Speed(0.3),
+                    Capabilities::default(),
+                    TownsfolkTask { task: TownsfolkTaskType::Idle },
+                    LodLevel::default(),
+                    SimBudget::default(),
+                    Transform::from_xyz(x, 0.0, z),
+                    GlobalTransform::default(),
+                )).with_children(|p| {
+                    for part in [
+                        BodyPartType::Torso,
+                        BodyPartType::Head,
+                        BodyPartType::Arm,
+                        BodyPartType::Arm,
+                        BodyPartType::Leg,
+                        BodyPartType::Leg,
+                    ] {
+                        p.spawn((BodyPart(part), LimbHealth(part.max_health()), Transform::IDENTITY));
+                    }
+                });
+            }
+
+            // Warm up, then time it
+            for _ in 0..10 {
+                app.update();
+            }
+            let start = Instant::now();
+            for _ in 0..FRAMES {
+                app.update();
+            }
+            let per_frame = start.elapsed() / FRAMES;
+            let ok = per_frame <= FRAME_BUDGET;
+            println!("{:>8} {:>10.3} {:>8}", n, per_frame.as_secs_f64() * 1000.0, if ok { "yes" } else { "no" });
+            if !ok || n >= 256_000 {
+                break;
+            }
+            n *= 2;
+        }
+    }
+}
@@ -1,54 +1,86 @@
 use bevy::prelude::*;
-use bevy::scene::SceneInstanceReady;
-use bevy::app::AppExit;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 
 use rand::prelude::*;
 use std::f32::consts::*;
 
 use crate::nim::NimPlugin;
+use crate::actions::{Action, ActionState, ActionsPlugin, MenuOpen};
+use crate::controls::ControlsPlugin;
+use crate::camera::CameraPlugin;
+use crate::vitals::VitalsPlugin;
+use crate::interact::InteractPlugin;
 use crate::player::PlayerPlugin;
 use crate::person::{
-    Pickable,
     PersonPlugin,
     SpawnPerson,
-    Jointy,
-    JointCycle,
-    GltfBodyPart
 };
+use crate::gltf_tags::GltfTagPlugin;
+use crate::limb::LimbPlugin;
+use crate::locomotion::LocomotionPlugin;
+use crate::ragdoll::RagdollPlugin;
+use crate::ik::FootIkPlugin;
+use crate::socket::SocketPlugin;
+use crate::corpse::CorpsePlugin;
+use crate::appearance::AppearancePlugin;
+use crate::crowd::CrowdPlugin;
+use crate::status::StatusPlugin;
+use crate::controller::ControllerPlugin;
+use crate::carry::CarryPlugin;
+use crate::tool::ToolPlugin;
+use crate::gun::GunPlugin;
+use crate::melee::MeleePlugin;
 use crate::town::TownPlugin;
 use crate::townsfolk::TownsfolkPlugin;
 use crate::ui::UiPlugin;
 use crate::bob::BobPlugin;
 use crate::hotbar::HotbarPlugin;
-use crate::terrain::Terrain;
+use crate::inventory_screen::InventoryScreenPlugin;
+use crate::pickup::PickupPlugin;
 
 pub struct GamePlugin;
 
 #[derive(Component)]
 pub struct Timey(pub f32);
 
-#[derive(Component)]
-struct GltfLoaded;
-
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(ActionsPlugin);
+        app.add_plugins(ControlsPlugin);
+        app.add_plugins(GltfTagPlugin);
         app.add_plugins(NimPlugin);
         app.add_plugins(PlayerPlugin);
+        app.add_plugins(CameraPlugin);
         app.add_plugins(PersonPlugin);
         app.add_plugins(TownPlugin);
         app.add_plugins(TownsfolkPlugin);
         app.add_plugins(UiPlugin);
         app.add_plugins(BobPlugin);
         app.add_plugins(HotbarPlugin);
+        app.add_plugins(InventoryScreenPlugin);
+        app.add_plugins(PickupPlugin);
+        app.add_plugins(LimbPlugin);
+        app.add_plugins(LocomotionPlugin);
+        app.add_plugins(RagdollPlugin);
+        app.add_plugins(FootIkPlugin);
+        app.add_plugins(SocketPlugin);
+        app.add_plugins(CorpsePlugin);
+        app.add_plugins(AppearancePlugin);
+        app.add_plugins(CrowdPlugin);
+        app.add_plugins(StatusPlugin);
+        app.add_plugins(ControllerPlugin);
+        app.add_plugins(CarryPlugin);
+        app.add_plugins(ToolPlugin);
+        app.add_plugins(GunPlugin);
+        app.add_plugins(MeleePlugin);
+        app.add_plugins(VitalsPlugin);
+        app.add_plugins(InteractPlugin);
 
         app.add_systems(Startup, (setup_scene, cursor_grab));
         app.add_systems(Update, (
             update_timers,
-            exit_system
+            toggle_cursor
         ));
-
-        app.add_observer(tag_gltf_heirachy);
     }
 }
 
@@ -61,25 +93,23 @@ fn cursor_grab(
     primary_window.cursor_options.visible = false;
 }
 
-fn exit_system(
-    mut exit: EventWriter<AppExit>,
-    input: Res<ButtonInput<KeyCode>>,
+fn toggle_cursor(
+    actions: Res<ActionState>,
+    menu: Res<MenuOpen>,
     mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
 ) {
-
-    if input.pressed(KeyCode::Tab) {
-        let mut primary_window = q_windows.single_mut();
-        if primary_window.cursor_options.grab_mode == CursorGrabMode::Locked {
-            primary_window.cursor_options.grab_mode = CursorGrabMode::None;
-            primary_window.cursor_options.visible = true;
-        } else {
-            primary_window.cursor_options.grab_mode = CursorGrabMode::Locked;
-            primary_window.cursor_options.visible = false;
-        }
+    // The menu needs the cursor
+    if menu.is_open() || !actions.just_pressed(Action::ToggleCursor) {
+        return;
     }
 
-    if input.pressed(KeyCode::Escape) {
-        exit.send(AppExit::Success);
+    let mut primary_window = q_windows.single_mut();
+    if primary_window.cursor_options.grab_mode == CursorGrabMode::Locked {
+        primary_window.cursor_options.grab_mode = CursorGrabMode::None;
+        primary_window.cursor_options.visible = true;
+    } else {
+        primary_window.cursor_options.grab_mode = CursorGrabMode::Locked;
+        primary_window.cursor_options.visible = false;
     }
 }
 
@@ -115,71 +145,6 @@ fn setup_scene(
         let speed = rng.gen_range(0.2..0.4);
         let dir = Vec3::new(rng.gen_range(-1.0..1.0), 0.0, rng.gen_range(-1.0..1.0)).normalize();
         info!("{:?}", dir);
-        commands.trigger(SpawnPerson { pos, speed, normal: dir });
-    }
-}
-
-fn tag_gltf_heirachy(
-    trigger: Trigger<SceneInstanceReady>,
-    mut commands: Commands,
-    children: Query<&Children>,
-    //body_parts: Query<(Option<&Timey>, Option<&Name>), (With<SceneRoot>, With<GltfBodyPart>)>,
-    body_parts: Query<(Option<&Timey>, Option<&Name>), With<SceneRoot>>,
-    deets: Query<(&GlobalTransform, &Parent, Option<&Name>)>,
-) {
-    let root = trigger.entity();
-
-    commands.entity(root).insert(GltfLoaded);
-
-    if let Ok(parts) = body_parts.get(root) {
-        let (timey, nameo) = parts;
-        info!("Scene: {:?}", nameo.map_or("-",|v|v));
-        let offset: f32 = timey.map_or(0.0, |Timey(v)| *v);
-
-        for entity in children.iter_descendants(root) {
-            info!("bodyparts i: {}", entity);
-            if let Ok((transform, parent, name)) = deets.get(entity) {
-                let name = name.map_or("-", |v|v);
-                //info!("n: {} {:?}", name, parent);
-                if name == "forearm" {
-                    commands.entity(entity).insert((Jointy, Timey(0.0)));
-                }
-                if name == "shoulder" {
-                    commands.entity(entity).insert((Jointy, Timey(10.0)));
-                }
-                if name == "hand" {
-                    commands.entity(entity).insert((Jointy, Timey(3.0)));
-                }
-                if name.ends_with("Mesh") {
-                    commands.entity(entity).insert(Pickable);
-                }
-                if name.ends_with("Floor") {
-                    commands.entity(entity).insert(Terrain);
-                }
-                if name == "HeadBone" {
-                    commands.entity(entity).insert((JointCycle, Timey(3.0), Pickable));
-                }
-                if name == "SerheadBone" {
-                    commands.entity(entity).insert((Jointy, Timey(3.0), Pickable));
-                }
-
-                if offset > 0.0 && name == "LegLowerBone" {
-                    commands.entity(entity).insert((JointCycle, Timey(offset)));
-                }
-            }
-
-        }
-
-    }
-}
-
-/*
-fn gltf_bodypart_matcher(
-    name: &str
-) {
-    match name {
-        "forearm" => (Jointy, Timey(0.0)),
-        _ if name.starts_with("Floor") => (Terrain)
+        commands.trigger(SpawnPerson { pos, speed, normal: dir, seed: rng.gen() });
     }
 }
-*/
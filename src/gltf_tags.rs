@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use bevy::ecs::system::EntityCommands;
+use bevy::scene::SceneInstanceReady;
+
+use crate::game::Timey;
+
+pub struct GltfTagPlugin;
+
+#[derive(Component)]
+pub struct GltfLoaded;
+
+/// How to match a GLTF node name
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    /// `*` matches any run of characters, `?` matches one
+    Glob(String),
+}
+
+impl NamePattern {
+    pub fn exact(s: &str) -> Self { Self::Exact(s.to_string()) }
+    pub fn prefix(s: &str) -> Self { Self::Prefix(s.to_string()) }
+    pub fn suffix(s: &str) -> Self { Self::Suffix(s.to_string()) }
+    pub fn glob(s: &str) -> Self { Self::Glob(s.to_string()) }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(p) => name == p,
+            Self::Prefix(p) => name.starts_with(p.as_str()),
+            Self::Suffix(p) => name.ends_with(p.as_str()),
+            Self::Glob(p) => glob_match(p.as_bytes(), name.as_bytes()),
+        }
+    }
+}
+
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pattern[1..], name)
+                || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// What a tag gets to know about the node it's tagging
+pub struct GltfTagContext<'a> {
+    /// The scene root that just finished spawning
+    pub root: Entity,
+    pub name: &'a str,
+    /// `Timey` on the scene root, for offsetting animation cycles
+    pub offset: f32,
+}
+
+type GltfTag = Box<dyn Fn(&mut EntityCommands, &GltfTagContext) + Send + Sync>;
+
+#[derive(Resource, Default)]
+pub struct GltfTagRegistry {
+    tags: Vec<(NamePattern, GltfTag)>,
+}
+
+impl GltfTagRegistry {
+    pub fn register(
+        &mut self,
+        pattern: NamePattern,
+        tag: impl Fn(&mut EntityCommands, &GltfTagContext) + Send + Sync + 'static,
+    ) {
+        self.tags.push((pattern, Box::new(tag)));
+    }
+}
+
+pub trait GltfTagAppExt {
+    /// Run `tag` on every node in a loaded scene whose name matches `pattern`
+    fn register_gltf_tag(
+        &mut self,
+        pattern: NamePattern,
+        tag: impl Fn(&mut EntityCommands, &GltfTagContext) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl GltfTagAppExt for App {
+    fn register_gltf_tag(
+        &mut self,
+        pattern: NamePattern,
+        tag: impl Fn(&mut EntityCommands, &GltfTagContext) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.init_resource::<GltfTagRegistry>();
+        self.world_mut()
+            .resource_mut::<GltfTagRegistry>()
+            .register(pattern, tag);
+        self
+    }
+}
+
+impl Plugin for GltfTagPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GltfTagRegistry>();
+        app.add_observer(tag_gltf_heirachy);
+    }
+}
+
+fn tag_gltf_heirachy(
+    trigger: Trigger<SceneInstanceReady>,
+    registry: Res<GltfTagRegistry>,
+    mut commands: Commands,
+    children: Query<&Children>,
+    scenes: Query<(Option<&Timey>, Option<&Name>), With<SceneRoot>>,
+    names: Query<&Name>,
+) {
+    let root = trigger.entity();
+
+    commands.entity(root).insert(GltfLoaded);
+
+    let Ok((timey, scene_name)) = scenes.get(root) else {
+        return;
+    };
+    info!("Scene: {:?}", scene_name.map_or("-", |v| v));
+    let offset: f32 = timey.map_or(0.0, |Timey(v)| *v);
+
+    for entity in children.iter_descendants(root) {
+        let Ok(name) = names.get(entity) else {
+            continue;
+        };
+        let ctx = GltfTagContext {
+            root,
+            name: name.as_str(),
+            offset,
+        };
+
+        for (pattern, tag) in registry.tags.iter() {
+            if pattern.matches(ctx.name) {
+                tag(&mut commands.entity(entity), &ctx);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, name: &str) -> bool {
+        NamePattern::glob(pattern).matches(name)
+    }
+
+    #[test]
+    fn glob_without_wildcards_is_exact() {
+        assert!(glob("HeadBone", "HeadBone"));
+        assert!(!glob("HeadBone", "HeadBones"));
+        assert!(!glob("HeadBone", "Head"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob("*Mesh", "ArmMesh"));
+        assert!(glob("*Mesh", "Mesh"));
+        assert!(glob("Leg*Bone", "LegUpperBone"));
+        assert!(glob("Leg*Bone", "LegBone"));
+        assert!(!glob("Leg*Bone", "LegUpperBones"));
+        assert!(glob("*", ""));
+        assert!(glob("**", "anything"));
+    }
+
+    #[test]
+    fn star_backtracks() {
+        assert!(glob("*Bone*Bone", "LegBoneFootBone"));
+        assert!(glob("a*b*c", "aXbYbZc"));
+        assert!(!glob("a*b*c", "aXbYcZ"));
+    }
+
+    #[test]
+    fn question_mark_matches_exactly_one() {
+        assert!(glob("leg?", "leg1"));
+        assert!(!glob("leg?", "leg"));
+        assert!(!glob("leg?", "leg12"));
+        assert!(glob("?*", "x"));
+        assert!(!glob("?*", ""));
+    }
+
+    #[test]
+    fn other_patterns() {
+        assert!(NamePattern::prefix("Bed").matches("BedFrame"));
+        assert!(!NamePattern::prefix("Bed").matches("TheBed"));
+        assert!(NamePattern::suffix("Mesh").matches("HeadMesh"));
+        assert!(NamePattern::exact("hand").matches("hand"));
+        assert!(!NamePattern::exact("hand").matches("hands"));
+    }
+}
@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+use std::f32::consts::*;
+
+use crate::inventory::{Inventory, ItemId};
+use crate::person::{Carried, Carryable, HitBodyPart, Knockback, Person};
+use crate::physics::{impulse, Mass, GRAVITY};
+use crate::player::{Cursor, Player};
+use crate::interact::InteractLayers;
+use crate::tool::{Tool, ToolAppExt, ToolTarget, ToolUse, Viewmodel};
+
+pub struct GunPlugin;
+
+const MAGAZINE_SIZE: u32 = 6;
+const RELOAD_SECS: f32 = 1.5;
+const MUZZLE_SPEED: f32 = 80.0;
+/// Bullets are gone after this long, hit or not
+const BULLET_LIFE: f32 = 4.0;
+const BULLET_POWER: f32 = 10.0;
+/// Where bullets leave from, relative to the shooter
+const MUZZLE: Vec3 = Vec3::new(0.0, 1.5, 0.0);
+
+/// Rounds loaded in the shooter's gun
+#[derive(Component)]
+pub struct Magazine {
+    pub rounds: u32,
+    /// Seconds left until the reload is done
+    pub reloading: Option<f32>,
+}
+
+impl Magazine {
+    /// Loaded and ready to fire
+    pub fn full() -> Self {
+        Self { rounds: MAGAZINE_SIZE, reloading: None }
+    }
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    pub velocity: Vec3,
+    pub power: f32,
+    pub item_id: ItemId,
+    /// Who fired it, so it doesn't hit them
+    pub shooter: Entity,
+    life: f32,
+}
+
+#[derive(Debug, Event)]
+pub struct FireGun {
+    pub dir: Dir3
+}
+
+#[derive(Debug, Event)]
+pub struct ReloadGun;
+
+#[derive(Resource)]
+struct BulletAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+struct GunTool;
+
+impl Tool for GunTool {
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        commands.trigger_targets(FireGun { dir: ctx.dir }, ctx.user);
+    }
+
+    fn secondary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        commands.trigger_targets(ReloadGun, ctx.user);
+    }
+
+    fn cooldown(&self) -> f32 {
+        0.25
+    }
+
+    fn reach(&self) -> f32 {
+        f32::INFINITY
+    }
+
+    fn verb(&self, _target: &ToolTarget) -> Option<String> {
+        Some("shoot".to_string())
+    }
+
+    /// Only worth pointing out things a bullet does something to
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::PERSON | InteractLayers::BODY_PART | InteractLayers::PROP
+    }
+
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        Some(Viewmodel {
+            scene: "gun.glb",
+            transform: Transform::from_xyz(0.65, 0.6, -1.75)
+                .with_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, -PI/2.5, 0.))
+                .with_scale(Vec3::splat(2.0)),
+        })
+    }
+}
+
+impl Plugin for GunPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_tool(ItemId::Gun, GunTool);
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, (
+            reload,
+            fly_projectiles
+        ));
+        app.add_observer(fire_gun);
+        app.add_observer(reload_gun);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(BulletAssets {
+        mesh: meshes.add(Sphere::new(0.04)),
+        material: materials.add(StandardMaterial {
+            base_color_texture: Some(asset_server.load("bullet.png")),
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+fn fire_gun(
+    trigger: Trigger<FireGun>,
+    bullets: Res<BulletAssets>,
+    mut shooters: Query<(&GlobalTransform, &mut Magazine)>,
+    mut commands: Commands,
+) {
+    let shooter = trigger.entity();
+    let Ok((t, mut mag)) = shooters.get_mut(shooter) else {
+        return;
+    };
+    if mag.reloading.is_some() {
+        return;
+    }
+    if mag.rounds == 0 {
+        info!("click");
+        commands.trigger_targets(ReloadGun, shooter);
+        return;
+    }
+    mag.rounds -= 1;
+
+    let dir = trigger.event().dir;
+    let origin = t.translation() + MUZZLE + *dir * 0.5;
+    commands.spawn((
+        Name::new("Bullet"),
+        Projectile {
+            velocity: *dir * MUZZLE_SPEED,
+            power: BULLET_POWER,
+            item_id: ItemId::Gun,
+            shooter,
+            life: BULLET_LIFE,
+        },
+        Mesh3d(bullets.mesh.clone()),
+        MeshMaterial3d(bullets.material.clone()),
+        Transform::from_translation(origin)
+    ));
+}
+
+fn reload_gun(
+    trigger: Trigger<ReloadGun>,
+    mut shooters: Query<(&mut Magazine, &Inventory)>,
+) {
+    let Ok((mut mag, inv)) = shooters.get_mut(trigger.entity()) else {
+        return;
+    };
+    if mag.reloading.is_some() || mag.rounds >= MAGAZINE_SIZE {
+        return;
+    }
+    if inv.count(ItemId::Ammo) == 0 {
+        info!("out of ammo");
+        return;
+    }
+    info!("reloading");
+    mag.reloading = Some(RELOAD_SECS);
+}
+
+/// Rounds come out of the inventory once the reload finishes
+fn reload(
+    time: Res<Time>,
+    mut shooters: Query<(&mut Magazine, &mut Inventory)>,
+) {
+    let dt = time.delta_secs();
+    for (mut mag, mut inv) in shooters.iter_mut() {
+        let Some(left) = mag.reloading.as_mut() else {
+            continue;
+        };
+        *left -= dt;
+        if *left > 0.0 {
+            continue;
+        }
+        mag.reloading = None;
+        let loaded = inv.remove(ItemId::Ammo, MAGAZINE_SIZE - mag.rounds);
+        mag.rounds += loaded;
+        info!("loaded {}, {} left", mag.rounds, inv.count(ItemId::Ammo));
+    }
+}
+
+/// Move bullets along, sweeping a ray over each step so fast ones
+/// can't skip through thin things
+#[allow(clippy::too_many_arguments)]
+fn fly_projectiles(
+    time: Res<Time>,
+    mut ray_cast: MeshRayCast,
+    mut bullets: Query<(Entity, &mut Transform, &mut Projectile)>,
+    projectiles: Query<(), With<Projectile>>,
+    players: Query<(), With<Player>>,
+    cursors: Query<(), With<Cursor>>,
+    props: Query<Option<&Mass>, (With<Carryable>, Without<Person>, Without<Carried>)>,
+    parent_q: Query<&Parent>,
+    persons: Query<(), With<Person>>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_secs();
+    for (e, mut t, mut bullet) in bullets.iter_mut() {
+        bullet.life -= dt;
+        if bullet.life <= 0.0 {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+
+        bullet.velocity.y -= GRAVITY * dt;
+        let step = bullet.velocity * dt;
+        let Ok(dir) = Dir3::new(step) else {
+            continue;
+        };
+
+        let shooter = bullet.shooter;
+        let filter = |entity: Entity| {
+            !projectiles.contains(entity)
+                && !players.contains(entity)
+                && !cursors.contains(entity)
+                && !parent_q.iter_ancestors(entity).any(|a| a == shooter || players.contains(a))
+        };
+        let settings = RayCastSettings::default()
+            .with_filter(&filter)
+            .with_visibility(RayCastVisibility::Any);
+        let hit = ray_cast
+            .cast_ray(Ray3d::new(t.translation, dir), &settings)
+            .first()
+            .filter(|(_, hit)| hit.distance <= step.length())
+            .map(|(mesh, hit)| (*mesh, hit.point));
+
+        let Some((mesh, point)) = hit else {
+            t.translation += step;
+            continue;
+        };
+
+        t.translation = point;
+        let root = parent_q.root_ancestor(mesh);
+        if persons.contains(root) {
+            commands.trigger_targets(
+                HitBodyPart { item_id: bullet.item_id, dir, power: bullet.power, by: Some(bullet.shooter) },
+                mesh
+            );
+        } else if let Ok(mass) = props.get(root) {
+            let velocity = impulse(bullet.power, *dir, mass);
+            commands.entity(root).insert(Knockback { velocity, grounded: false });
+        }
+        commands.entity(e).despawn_recursive();
+    }
+}
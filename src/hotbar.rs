@@ -1,13 +1,14 @@
 use bevy::prelude::*;
-use bevy::input::mouse::MouseWheel;
-use crate::inventory::{Inventory,ItemId};
+use bevy::ui::RelativeCursorPosition;
+use crate::inventory::Inventory;
 use crate::player::Player;
+use crate::actions::{Action, ActionState};
 
-#[derive(Component)]
-pub struct HotbarSelected(pub u32);
+/// Inventory slots from 0 up to this are on the hotbar
+pub const HOTBAR_SLOTS: u32 = 6;
 
 #[derive(Component)]
-pub struct HotbarScrollAmount(pub f32);
+pub struct HotbarSelected(pub u32);
 
 #[derive(Component)]
 pub struct SlotId(pub u32);
@@ -22,7 +23,10 @@ pub struct HotbarPlugin;
 impl Plugin for HotbarPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup);
-        app.add_systems(Update, scroll_hotbar);
+        app.add_systems(Update, (
+            scroll_hotbar,
+            refresh_hotbar
+        ));
         app.add_observer(hotbar_change_selected);
     }
 }
@@ -34,13 +38,12 @@ fn setup(
     commands.spawn((
         Name::new("Hotbar"),
         HotbarSelected(0),
-        HotbarScrollAmount(0.0),
         Node {
             position_type: PositionType::Absolute,
             left: Val::Percent(50.0),
             top: Val::Percent(100.0),
             margin: UiRect {
-                left: Val::Px(-150.0), // Offset to center
+                left: Val::Px(-25.0 * HOTBAR_SLOTS as f32), // Offset to center
                 top: Val::Px(-50.0),
                 ..default()
             },
@@ -48,7 +51,7 @@ fn setup(
         }
     )).with_children(|p| {
 
-        for i in 0..5 {
+        for i in 0..HOTBAR_SLOTS {
             p.spawn((
                 Name::new("slot0"),
                 SlotId(i),
@@ -65,7 +68,9 @@ fn setup(
                     ..default()
                 },
                 Text::new("."),
-                BackgroundColor(Srgba::hex("#555555").unwrap().into())
+                BackgroundColor(Srgba::hex("#555555").unwrap().into()),
+                // Somewhere to drop things from the inventory screen
+                RelativeCursorPosition::default()
             ));
 
         }
@@ -77,36 +82,25 @@ fn setup(
 
 
 fn scroll_hotbar(
-    mut evr_scroll: EventReader<MouseWheel>,
+    actions: Res<ActionState>,
     mut hotbar: Query<&mut HotbarSelected>,
-    mut scroll: Query<&mut HotbarScrollAmount>,
     mut commands: Commands
 ) {
     let mut selected = hotbar.single_mut();
-    let mut scroll = scroll.single_mut();
 
-    use bevy::input::mouse::MouseScrollUnit;
     let mut yo = 0;
-    for ev in evr_scroll.read() {
-        match ev.unit {
-            MouseScrollUnit::Line => {
-                yo = (-1.0 * ev.y.signum()) as i32;
-            }
-            MouseScrollUnit::Pixel => {
-                scroll.0 += ev.y;
-                if scroll.0.abs() > 50.0 {
-                    yo = (-1.0 * scroll.0.signum()) as i32;
-                    scroll.0 = 0.0;
-                }
-            }
-        }
+    if actions.just_pressed(Action::NextSlot) {
+        yo += 1;
+    }
+    if actions.just_pressed(Action::PrevSlot) {
+        yo -= 1;
     }
 
     if yo == 0 { return };
     let cur = selected.0;
     let mut next = cur;
     if yo > 0 {
-        if cur < 4 {
+        if cur < HOTBAR_SLOTS - 1 {
             next = cur + 1;
         } else {
             next = 0;
@@ -115,7 +109,7 @@ fn scroll_hotbar(
         if cur > 0 {
             next = cur - 1;
         } else {
-            next = 4;
+            next = HOTBAR_SLOTS - 1;
         }
     }
     selected.0 = next;
@@ -125,6 +119,21 @@ fn scroll_hotbar(
 
 }
 
+/// Slot text goes stale when the inventory changes under it
+fn refresh_hotbar(
+    hotbar: Query<&HotbarSelected>,
+    inv: Query<(), (With<Player>, Changed<Inventory>)>,
+    mut commands: Commands
+) {
+    if inv.is_empty() {
+        return;
+    }
+    let Ok(selected) = hotbar.get_single() else {
+        return;
+    };
+    commands.trigger(HotbarChangeSelected { slot_id: selected.0 });
+}
+
 fn hotbar_change_selected(
     trigger: Trigger<HotbarChangeSelected>,
     mut slots: Query<(&SlotId, &mut Text, &mut BackgroundColor)>,
@@ -134,11 +143,7 @@ fn hotbar_change_selected(
     let inv_player = inv.single();
 
     for (slot, mut text, mut bg) in slots.iter_mut() {
-        let sm = inv_player.map.get(&slot.0);
-        **text = format!("{:?} {}", sm.map(|s| s.item_id).unwrap_or(ItemId::Fist), match sm {
-            Some(&s) => s.num,
-            _=> 0
-        });
+        **text = inv_player.map.get(&slot.0).map_or(String::new(), |s| s.label());
         bg.0 = Srgba::hex("#555555").unwrap().into();
         if slot.0 == selected {
             bg.0 = Color::BLACK;
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use std::collections::HashMap;
+
+use crate::gltf_tags::{GltfTagAppExt, NamePattern};
+use crate::crowd::LodLevel;
+use crate::locomotion::Locomotion;
+use crate::person::{person_of, Carried, Knockback};
+use crate::terrain::Terrain;
+use crate::controller::ground_below;
+use crate::physics::to_parent_space;
+
+pub struct FootIkPlugin;
+
+/// How quickly the hips follow the ground
+const HIP_RATE: f32 = 10.0;
+
+/// Upper bone of a two bone leg chain, the lower bone is its child.
+/// `root` is the person the leg is walking around on.
+#[derive(Component)]
+pub struct LegIk {
+    pub root: Entity,
+    rest: Option<Quat>,
+    /// World height of the ground under the foot, as of the last pose
+    ground: Option<f32>,
+}
+
+impl LegIk {
+    pub fn new(root: Entity) -> Self {
+        Self { root, rest: None, ground: None }
+    }
+}
+
+impl Plugin for FootIkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (reset_legs, adjust_hips));
+        app.register_gltf_tag(NamePattern::exact("LegUpperBone"), |e, _| {
+            e.queue(|entity: Entity, world: &mut World| {
+                if let Some(person) = person_of(world, entity) {
+                    world.entity_mut(entity).insert(LegIk::new(person));
+                }
+            });
+        });
+        // Runs on the animated pose, after it has been propagated, and
+        // patches up the leg's globals itself so it shows this frame.
+        app.add_systems(
+            PostUpdate,
+            (find_ground, solve_legs).chain().after(TransformSystem::TransformPropagate)
+        );
+    }
+}
+
+/// World position of the end of a bone, which runs along its +Y
+fn bone_end(gt: &GlobalTransform, length: f32) -> Vec3 {
+    gt.transform_point(Vec3::Y * length)
+}
+
+/// Nothing animates the upper bone, so put it back before solving again
+fn reset_legs(
+    mut legs: Query<(&mut LegIk, &mut Transform)>,
+) {
+    for (mut leg, mut t) in legs.iter_mut() {
+        let rest = *leg.rest.get_or_insert(t.rotation);
+        t.rotation = rest;
+    }
+}
+
+/// Where the ground is under each foot, floors included. Looks down from
+/// the hip so a foot that's already sunk in still finds what it's in.
+fn find_ground(
+    mut ray_cast: MeshRayCast,
+    terrain: Query<(), With<Terrain>>,
+    mut legs: Query<(&mut LegIk, &GlobalTransform, &Children)>,
+    bones: Query<(&Transform, &GlobalTransform), Without<LegIk>>,
+) {
+    for (mut leg, upper_gt, children) in legs.iter_mut() {
+        let Some((lower_t, lower_gt)) = children.iter().find_map(|c| bones.get(*c).ok()) else {
+            continue;
+        };
+        let foot = bone_end(lower_gt, lower_t.translation.length());
+        let top = upper_gt.translation().y.max(foot.y);
+        leg.ground = Some(ground_below(&mut ray_cast, &terrain, foot, top).0);
+    }
+}
+
+/// Drop the hips so the lowest foot is on the ground. The others get lifted by IK.
+fn adjust_hips(
+    time: Res<Time>,
+    legs: Query<&LegIk>,
+    globals: Query<&GlobalTransform>,
+    mut roots: Query<(&mut Transform, &GlobalTransform, Option<&Parent>), (With<Locomotion>, Without<Carried>, Without<Knockback>)>,
+) {
+    let mut lowest: HashMap<Entity, f32> = HashMap::new();
+    for leg in legs.iter() {
+        let Some(ground) = leg.ground else {
+            continue;
+        };
+        let h = lowest.entry(leg.root).or_insert(ground);
+        *h = h.min(ground);
+    }
+
+    let k = (time.delta_secs() * HIP_RATE).min(1.0);
+    for (root, ground) in lowest {
+        let Ok((mut t, gt, parent)) = roots.get_mut(root) else {
+            continue;
+        };
+        let pos = gt.translation();
+        let parent_gt = parent.and_then(|p| globals.get(p.get()).ok());
+        let target = to_parent_space(parent_gt, Vec3::new(pos.x, ground, pos.z));
+        t.translation = t.translation.lerp(target, k);
+    }
+}
+
+fn solve_legs(
+    legs: Query<(Entity, &LegIk, &Children)>,
+    roots: Query<(&GlobalTransform, Option<&LodLevel>), With<Locomotion>>,
+    mut bones: Query<(&mut Transform, &mut GlobalTransform), Without<Locomotion>>,
+) {
+    for (upper, leg, children) in legs.iter() {
+        let Ok((root, lod)) = roots.get(leg.root) else {
+            continue;
+        };
+        // Nobody can see feet from far away
+        if lod.is_some_and(|l| *l != LodLevel::Near) {
+            continue;
+        }
+        let Some(&lower) = children.iter().find(|c| bones.contains(**c)) else {
+            continue;
+        };
+        let Ok([(mut upper_t, mut upper_gt), (mut lower_t, mut lower_gt)]) =
+            bones.get_many_mut([upper, lower]) else {
+            continue;
+        };
+
+        let hip = upper_gt.translation();
+        let knee = lower_gt.translation();
+        let foot = bone_end(&lower_gt, lower_t.translation.length());
+
+        // Only ever lift a foot out of the ground, the hips handle the rest
+        let Some(ground) = leg.ground else {
+            continue;
+        };
+        if foot.y >= ground {
+            continue;
+        }
+        let target = Vec3::new(foot.x, ground, foot.z);
+
+        let l1 = hip.distance(knee);
+        let l2 = knee.distance(foot);
+        let to_target = target - hip;
+        let d = to_target.length().clamp((l1 - l2).abs() + 0.001, (l1 + l2) * 0.999);
+        let Ok(reach) = Dir3::new(to_target) else {
+            continue;
+        };
+
+        // Law of cosines for the angle at the hip, knees bend forward
+        let hip_angle = ((l1 * l1 + d * d - l2 * l2) / (2.0 * l1 * d)).clamp(-1.0, 1.0).acos();
+        let bend = root.right();
+        let upper_dir = Quat::from_axis_angle(*bend, hip_angle) * *reach;
+        let new_knee = hip + upper_dir * l1;
+        let lower_dir = (target - new_knee).normalize_or_zero();
+
+        let upper_delta = Quat::from_rotation_arc((knee - hip).normalize_or_zero(), upper_dir);
+        let bent_lower = upper_delta * (foot - knee).normalize_or_zero();
+        let lower_delta = Quat::from_rotation_arc(bent_lower, lower_dir);
+
+        // Turn the world space corrections into local rotations
+        let (scale, upper_rot, _) = upper_gt.to_scale_rotation_translation();
+        let parent_rot = upper_rot * upper_t.rotation.inverse();
+        let new_upper_rot = upper_delta * upper_rot;
+        upper_t.rotation = (parent_rot.inverse() * new_upper_rot).normalize();
+
+        let (lower_scale, lower_rot, _) = lower_gt.to_scale_rotation_translation();
+        let new_lower_rot = lower_delta * upper_delta * lower_rot;
+        lower_t.rotation = (new_upper_rot.inverse() * new_lower_rot).normalize();
+
+        *upper_gt = GlobalTransform::from(
+            Transform::from_translation(hip)
+                .with_rotation(new_upper_rot)
+                .with_scale(scale)
+        );
+        *lower_gt = GlobalTransform::from(
+            Transform::from_translation(new_knee)
+                .with_rotation(new_lower_rot)
+                .with_scale(lower_scale)
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+
+use crate::carry::PICKUP_REACH;
+use crate::inventory::ItemStack;
+use crate::person::{Carried, Carryable, Person, Pickable};
+use crate::player::{Player, RaycastTarget};
+use crate::tool::{tool_target, ToolRegistry, ToolState};
+
+pub struct InteractPlugin;
+
+/// How much brighter the thing being aimed at gets
+const HIGHLIGHT: LinearRgba = LinearRgba::rgb(0.25, 0.25, 0.1);
+
+/// What kind of thing something is, as far as tools are concerned.
+/// The nearest layers up its hierarchy decide, except that anything
+/// on a person counts as the person.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteractLayers(u8);
+
+impl InteractLayers {
+    pub const PERSON: Self = Self(1 << 0);
+    /// Loose body parts, not ones on a person
+    pub const BODY_PART: Self = Self(1 << 1);
+    pub const TERRAIN: Self = Self(1 << 2);
+    /// Anything else that's lying around
+    pub const PROP: Self = Self(1 << 3);
+    pub const BUILDING: Self = Self(1 << 4);
+    pub const ALL: Self = Self(u8::MAX);
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl std::ops::BitOr for InteractLayers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The nearest thing a ray hit
+#[derive(Debug, Clone, Copy)]
+pub struct InteractHit {
+    pub mesh: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+    /// `point` and `normal` in the mesh's local space
+    pub mesh_point: Vec3,
+    pub mesh_normal: Vec3,
+}
+
+/// Finds what can be interacted with along a ray
+#[derive(SystemParam)]
+pub struct Interactables<'w, 's> {
+    ray_cast: MeshRayCast<'w, 's>,
+    pickable: Query<'w, 's, &'static GlobalTransform, With<Pickable>>,
+    carried: Query<'w, 's, (), With<Carried>>,
+    layers: Query<'w, 's, &'static InteractLayers>,
+    persons: Query<'w, 's, (), With<Person>>,
+    parent_q: Query<'w, 's, &'static Parent>,
+}
+
+impl Interactables<'_, '_> {
+    /// The nearest `Pickable` no further than `reach`, skipping anything
+    /// being carried, if it's on one of `layers`. Things on other layers
+    /// still get in the way.
+    pub fn nearest(&mut self, ray: Ray3d, reach: f32, layers: InteractLayers) -> Option<InteractHit> {
+        let Self { ray_cast, pickable, carried, layers: layer_q, persons, parent_q } = self;
+        let filter = |entity| {
+            pickable.contains(entity)
+                && !parent_q.iter_ancestors(entity).any(|e| carried.contains(e))
+        };
+        let settings = RayCastSettings::default()
+            .with_filter(&filter);
+
+        let (mesh, hit) = ray_cast
+            .cast_ray(ray, &settings)
+            .first()
+            .filter(|(_, hit)| hit.distance <= reach)
+            .map(|(mesh, hit)| (*mesh, hit.clone()))?;
+        if !layers_of(layer_q, persons, parent_q, mesh).intersects(layers) {
+            return None;
+        }
+
+        // Hit position and normal to local space
+        let affine = pickable.get(mesh).ok()?.affine();
+        Some(InteractHit {
+            mesh,
+            point: hit.point,
+            normal: hit.normal,
+            distance: hit.distance,
+            mesh_point: affine.inverse().transform_point3(hit.point),
+            mesh_normal: (Mat3::from(affine.matrix3).transpose() * hit.normal).normalize_or_zero(),
+        })
+    }
+}
+
+/// The layers of the nearest thing up the hierarchy with some, or of
+/// the person it's part of. The walk stops at the person, since people
+/// grown by the cloner hang off the terrain. Anything without counts
+/// as a prop.
+fn layers_of(
+    layers: &Query<&InteractLayers>,
+    persons: &Query<(), With<Person>>,
+    parent_q: &Query<&Parent>,
+    entity: Entity,
+) -> InteractLayers {
+    let mut nearest = None;
+    for e in std::iter::once(entity).chain(parent_q.iter_ancestors(entity)) {
+        if persons.contains(e) {
+            return layers.get(e).copied().unwrap_or(InteractLayers::PERSON);
+        }
+        nearest = nearest.or(layers.get(e).ok().copied());
+    }
+    nearest.unwrap_or(InteractLayers::PROP)
+}
+
+/// Wears a tinted copy of its own material while aimed at. The copy
+/// goes when the highlight does.
+#[derive(Component)]
+pub(crate) struct Highlighted {
+    pub(crate) original: Handle<StandardMaterial>,
+}
+
+#[derive(Component)]
+struct Tooltip;
+
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, (
+            highlight_target,
+            show_tooltip
+        ));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        Name::new("Tooltip"),
+        Tooltip,
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(50.0),
+            top: Val::Percent(50.0),
+            margin: UiRect {
+                left: Val::Px(16.0),
+                top: Val::Px(16.0),
+                ..default()
+            },
+            ..default()
+        },
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+    ));
+}
+
+fn highlight_target(
+    ray_target: Res<RaycastTarget>,
+    mut current: Local<Option<Entity>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: Query<(&mut MeshMaterial3d<StandardMaterial>, Option<&Highlighted>)>,
+    mut commands: Commands,
+) {
+    if *current == ray_target.mesh {
+        return;
+    }
+
+    if let Some(old) = current.take() {
+        if let Ok((mut material, Some(highlighted))) = meshes.get_mut(old) {
+            material.0 = highlighted.original.clone();
+            commands.entity(old).remove::<Highlighted>();
+        }
+    }
+
+    let Some(new) = ray_target.mesh else {
+        return;
+    };
+    *current = Some(new);
+    let Ok((mut material, None)) = meshes.get_mut(new) else {
+        return;
+    };
+    let original = material.0.clone();
+    let mut tinted = materials.get(&original).cloned().unwrap_or_default();
+    tinted.emissive += HIGHLIGHT;
+    material.0 = materials.add(tinted);
+    commands.entity(new).insert(Highlighted { original });
+}
+
+/// What's being aimed at, and what the tool in hand would do to it
+#[allow(clippy::too_many_arguments)]
+fn show_tooltip(
+    ray_target: Res<RaycastTarget>,
+    registry: Res<ToolRegistry>,
+    player: Query<&ToolState, With<Player>>,
+    names: Query<&Name>,
+    parent_q: Query<&Parent>,
+    persons: Query<(), With<Person>>,
+    carryable: Query<(), (With<Carryable>, Without<Carried>)>,
+    items: Query<&ItemStack>,
+    mut tooltips: Query<&mut Text, With<Tooltip>>,
+) {
+    let Ok(mut text) = tooltips.get_single_mut() else {
+        return;
+    };
+    let Some(target) = tool_target(&ray_target, &parent_q, &persons) else {
+        if !text.is_empty() {
+            text.clear();
+        }
+        return;
+    };
+
+    let name = names
+        .get(target.root)
+        .or(names.get(target.mesh))
+        .map_or("Something", |n| n.as_str());
+    let mut tip = name.to_string();
+    if let Some(stack) = items.get(target.root).ok().filter(|s| s.num > 1) {
+        tip += &format!(" x{}", stack.num);
+    }
+    let tool = player
+        .get_single()
+        .ok()
+        .and_then(|s| s.item_id)
+        .and_then(|id| registry.get(id))
+        .filter(|t| ray_target.distance <= t.reach());
+    if let Some(verb) = tool.and_then(|t| t.verb(&target)) {
+        tip += &format!("\nUse: {}", verb);
+    }
+    let pickup = carryable.contains(target.root) || items.contains(target.root);
+    if pickup && ray_target.distance <= PICKUP_REACH {
+        tip += "\nInteract: pick up";
+    }
+    if **text != tip {
+        **text = tip;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+
+    type LayerQueries<'w, 's> = (
+        Query<'w, 's, &'static InteractLayers>,
+        Query<'w, 's, (), With<Person>>,
+        Query<'w, 's, &'static Parent>,
+    );
+
+    fn layers_in(world: &mut World, entity: Entity) -> InteractLayers {
+        let mut state: SystemState<LayerQueries> = SystemState::new(world);
+        let (layers, persons, parent_q) = state.get(world);
+        layers_of(&layers, &persons, &parent_q, entity)
+    }
+
+    #[test]
+    fn person_grown_on_terrain_is_a_person() {
+        let mut world = World::new();
+        let terrain = world.spawn(InteractLayers::TERRAIN).id();
+        let person = world.spawn((Person, InteractLayers::PERSON)).set_parent(terrain).id();
+        let mesh = world.spawn_empty().set_parent(person).id();
+        assert_eq!(layers_in(&mut world, mesh), InteractLayers::PERSON);
+        assert_eq!(layers_in(&mut world, terrain), InteractLayers::TERRAIN);
+    }
+
+    #[test]
+    fn part_stuck_on_a_person_is_the_person() {
+        let mut world = World::new();
+        let person = world.spawn((Person, InteractLayers::PERSON)).id();
+        let part = world.spawn(InteractLayers::BODY_PART).set_parent(person).id();
+        let mesh = world.spawn_empty().set_parent(part).id();
+        assert_eq!(layers_in(&mut world, mesh), InteractLayers::PERSON);
+    }
+
+    #[test]
+    fn part_stuck_on_terrain_keeps_its_own_layers() {
+        let mut world = World::new();
+        let terrain = world.spawn(InteractLayers::TERRAIN).id();
+        let part = world.spawn(InteractLayers::BODY_PART).set_parent(terrain).id();
+        let mesh = world.spawn_empty().set_parent(part).id();
+        assert_eq!(layers_in(&mut world, mesh), InteractLayers::BODY_PART);
+        let loose = world.spawn_empty().id();
+        assert_eq!(layers_in(&mut world, loose), InteractLayers::PROP);
+    }
+}
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use std::collections::HashMap;
 
 /// Type of body piece
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum BodyPartType {
     Head,
     Torso,
@@ -15,7 +15,8 @@ pub enum BodyPartType {
 pub enum ToolType {
     Fist,
     Sword,
-    Cloner
+    Cloner,
+    Gun
 }
 
 /// Type of item
@@ -46,10 +47,36 @@ pub enum ItemId {
     Apple,
     Fist,
     Sword,
-    Cloner
+    Cloner,
+    Gun,
+    Ammo
 }
 
+/// Slots from 0 up to this hold items
+pub const INVENTORY_SLOTS: u32 = 10;
+
 impl ItemId {
+    pub fn from_body_part(part: BodyPartType) -> Self {
+        match part {
+            BodyPartType::Head => Self::Head,
+            BodyPartType::Torso => Self::Torso,
+            BodyPartType::Leg => Self::Leg,
+            BodyPartType::Arm => Self::Arm,
+        }
+    }
+
+    /// Most of this item one slot can hold
+    pub fn max_stack(&self) -> u32 {
+        match self.get_default_type() {
+            ItemType::Tool(_) => 1,
+            ItemType::BodyPart(_) => 8,
+            ItemType::Generic => match *self {
+                Self::Ammo => 48,
+                _ => 16,
+            },
+        }
+    }
+
     pub fn get_default_type(&self) -> ItemType {
         match *self {
             Self::Head => ItemType::BodyPart(BodyPartType::Head),
@@ -59,13 +86,17 @@ impl ItemId {
             Self::Apple => ItemType::Generic,
             Self::Fist => ItemType::Tool(ToolType::Fist),
             Self::Sword => ItemType::Tool(ToolType::Sword),
-            Self::Cloner => ItemType::Tool(ToolType::Cloner)
+            Self::Cloner => ItemType::Tool(ToolType::Cloner),
+            Self::Gun => ItemType::Tool(ToolType::Gun),
+            Self::Ammo => ItemType::Generic
         }
     }
 }
 
 
-#[derive(Debug, Clone, Copy)]
+/// Some number of one item. In the world, one lying on the ground
+/// to be picked up.
+#[derive(Debug, Clone, Copy, Component)]
 pub struct ItemStack {
     pub item_id: ItemId,
     pub item_type: ItemType,
@@ -73,16 +104,29 @@ pub struct ItemStack {
 }
 
 impl ItemStack {
-    pub fn test() -> Self {
+    pub fn new(item_id: ItemId, num: u32) -> Self {
         Self {
-            item_id: ItemId::Leg,
-            item_type: ItemId::Leg.get_default_type(),
-            num: 2
+            item_id,
+            item_type: item_id.get_default_type(),
+            num
         }
     }
+
+    /// Short text for a slot: the item, and how many if it stacks
+    pub fn label(&self) -> String {
+        if self.item_id.max_stack() == 1 {
+            format!("{:?}", self.item_id)
+        } else {
+            format!("{:?}\n{}", self.item_id, self.num)
+        }
+    }
+
+    pub fn test() -> Self {
+        Self::new(ItemId::Leg, 2)
+    }
 }
 
-#[derive(Debug, Clone, Component)]
+#[derive(Debug, Clone, Default, Component)]
 pub struct Inventory {
     pub map: HashMap<u32, ItemStack>,
 }
@@ -94,26 +138,275 @@ impl Inventory {
         }
     }
 
-    pub fn add_item(&mut self, mut stack: ItemStack) {
-        for i in 0..10 {
-            let item_opt = self.map.get(&i);
-            if item_opt.is_some() {
-                let slot_stack = item_opt.expect("err empty");
-                if slot_stack.item_id != stack.item_id {
-                    continue;
+    /// Top up stacks of the same item first, then fill empty slots,
+    /// all in slot order. Returns how many didn't fit.
+    pub fn add_item(&mut self, stack: ItemStack) -> u32 {
+        let max = stack.item_id.max_stack();
+        let mut left = stack.num;
+        for i in 0..INVENTORY_SLOTS {
+            if left == 0 {
+                break;
+            }
+            if let Some(slot_stack) = self.map.get_mut(&i) {
+                if slot_stack.item_id == stack.item_id && slot_stack.num < max {
+                    let add = left.min(max - slot_stack.num);
+                    slot_stack.num += add;
+                    left -= add;
                 }
-                // add it
-                stack.num += slot_stack.num
             }
+        }
+        for i in 0..INVENTORY_SLOTS {
+            if left == 0 {
+                break;
+            }
+            if self.map.contains_key(&i) {
+                continue;
+            }
+            let add = left.min(max);
+            self.map.insert(i, ItemStack { num: add, ..stack });
+            left -= add;
+        }
+        left
+    }
+
+    /// How many more of an item would fit
+    pub fn space_for(&self, item_id: ItemId) -> u32 {
+        let max = item_id.max_stack();
+        (0..INVENTORY_SLOTS)
+            .map(|i| match self.map.get(&i) {
+                None => max,
+                Some(s) if s.item_id == item_id => max.saturating_sub(s.num),
+                Some(_) => 0,
+            })
+            .sum()
+    }
+
+    /// No slots left empty
+    pub fn is_full(&self) -> bool {
+        (0..INVENTORY_SLOTS).all(|i| self.map.contains_key(&i))
+    }
+
+    /// How many of an item there are across every slot
+    pub fn count(&self, item_id: ItemId) -> u32 {
+        self.map
+            .values()
+            .filter(|s| s.item_id == item_id)
+            .map(|s| s.num)
+            .sum()
+    }
 
-            let ins_stack = ItemStack {
-                item_id: stack.item_id,
-                item_type: stack.item_type,
-                num: stack.num
+    /// Take up to `num` of an item out of whichever slots have it,
+    /// emptying slots as they run out. Returns how many were taken.
+    pub fn remove(&mut self, item_id: ItemId, num: u32) -> u32 {
+        let mut slots: Vec<u32> = self.map
+            .iter()
+            .filter(|(_, s)| s.item_id == item_id)
+            .map(|(i, _)| *i)
+            .collect();
+        slots.sort();
+
+        let mut taken = 0;
+        for i in slots {
+            if taken == num {
+                break;
+            }
+            let Some(stack) = self.map.get_mut(&i) else {
+                continue;
             };
-            self.map.insert(i, ins_stack);
-            break;
+            let take = stack.num.min(num - taken);
+            stack.num -= take;
+            taken += take;
+            if stack.num == 0 {
+                self.map.remove(&i);
+            }
         }
+        taken
+    }
+
+    /// Take up to `num` out of one slot, emptying it if that's all of it
+    pub fn take_from_slot(&mut self, slot: u32, num: u32) -> Option<ItemStack> {
+        let stack = self.map.get_mut(&slot)?;
+        let take = stack.num.min(num);
+        if take == 0 {
+            return None;
+        }
+        stack.num -= take;
+        let taken = ItemStack { num: take, ..*stack };
+        if stack.num == 0 {
+            self.map.remove(&slot);
+        }
+        Some(taken)
+    }
+
+    /// Swap whatever is in two slots, either of which can be empty
+    pub fn swap_slots(&mut self, a: u32, b: u32) {
+        let from_a = self.map.remove(&a);
+        let from_b = self.map.remove(&b);
+        if let Some(s) = from_a {
+            self.map.insert(b, s);
+        }
+        if let Some(s) = from_b {
+            self.map.insert(a, s);
+        }
+    }
+
+    /// Put one slot's stack onto another. The same item merges as far
+    /// as it stacks, leaving the rest behind; anything else swaps.
+    pub fn move_stack(&mut self, from: u32, to: u32) {
+        if from == to {
+            return;
+        }
+        let (Some(a), Some(b)) = (self.map.get(&from).copied(), self.map.get(&to).copied()) else {
+            self.swap_slots(from, to);
+            return;
+        };
+        if a.item_id != b.item_id {
+            self.swap_slots(from, to);
+            return;
+        }
+        let moved = a.num.min(a.item_id.max_stack().saturating_sub(b.num));
+        if let Some(s) = self.map.get_mut(&to) {
+            s.num += moved;
+        }
+        self.take_from_slot(from, moved);
+    }
+
+    /// Move `num` off a stack into the first empty slot. Returns that
+    /// slot, or `None` if there's nowhere to put them or `num` isn't
+    /// less than the stack.
+    pub fn split_stack(&mut self, slot: u32, num: u32) -> Option<u32> {
+        let stack = self.map.get(&slot)?;
+        if num == 0 || num >= stack.num {
+            return None;
+        }
+        let empty = (0..INVENTORY_SLOTS).find(|i| !self.map.contains_key(i))?;
+        let taken = self.take_from_slot(slot, num)?;
+        self.map.insert(empty, taken);
+        Some(empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_of_swords() -> Inventory {
+        let mut inv = Inventory::new();
+        for _ in 0..INVENTORY_SLOTS {
+            inv.add_item(ItemStack::new(ItemId::Sword, 1));
+        }
+        inv
+    }
+
+    #[test]
+    fn add_merges_then_fills_empty_slots() {
+        let mut inv = Inventory::new();
+        inv.add_item(ItemStack::new(ItemId::Apple, 10));
+        inv.add_item(ItemStack::new(ItemId::Sword, 1));
+        assert_eq!(inv.add_item(ItemStack::new(ItemId::Apple, 10)), 0);
+        assert_eq!(inv.map[&0].num, 16);
+        assert_eq!(inv.map[&1].item_id, ItemId::Sword);
+        assert_eq!(inv.map[&2].num, 4);
+        assert_eq!(inv.count(ItemId::Apple), 20);
+    }
+
+    #[test]
+    fn add_returns_what_does_not_fit() {
+        let mut inv = full_of_swords();
+        assert!(inv.is_full());
+        assert_eq!(inv.add_item(ItemStack::new(ItemId::Apple, 3)), 3);
+        assert_eq!(inv.count(ItemId::Apple), 0);
+    }
+
+    #[test]
+    fn tools_do_not_stack() {
+        let mut inv = Inventory::new();
+        inv.add_item(ItemStack::new(ItemId::Gun, 2));
+        assert_eq!(inv.map[&0].num, 1);
+        assert_eq!(inv.map[&1].num, 1);
+    }
+
+    #[test]
+    fn space_counts_partial_stacks_and_empty_slots() {
+        let mut inv = full_of_swords();
+        inv.take_from_slot(9, 1);
+        assert!(!inv.is_full());
+        assert_eq!(inv.space_for(ItemId::Ammo), 48);
+        inv.add_item(ItemStack::new(ItemId::Ammo, 40));
+        assert_eq!(inv.space_for(ItemId::Ammo), 8);
+        assert_eq!(inv.space_for(ItemId::Sword), 0);
+    }
+
+    #[test]
+    fn remove_takes_across_slots() {
+        let mut inv = Inventory::new();
+        inv.add_item(ItemStack::new(ItemId::Leg, 8));
+        inv.add_item(ItemStack::new(ItemId::Leg, 3));
+        assert_eq!(inv.remove(ItemId::Leg, 10), 10);
+        assert_eq!(inv.count(ItemId::Leg), 1);
+        assert_eq!(inv.map.len(), 1);
+        assert_eq!(inv.remove(ItemId::Leg, 5), 1);
+        assert!(inv.map.is_empty());
+    }
+
+    #[test]
+    fn take_from_slot_empties_it() {
+        let mut inv = Inventory::new();
+        inv.add_item(ItemStack::new(ItemId::Head, 3));
+        let taken = inv.take_from_slot(0, 2).unwrap();
+        assert_eq!((taken.item_id, taken.num), (ItemId::Head, 2));
+        assert_eq!(inv.map[&0].num, 1);
+        assert_eq!(inv.take_from_slot(0, 5).unwrap().num, 1);
+        assert!(!inv.map.contains_key(&0));
+        assert!(inv.take_from_slot(0, 1).is_none());
+    }
+
+    #[test]
+    fn swap_moves_into_empty_slots() {
+        let mut inv = Inventory::new();
+        inv.add_item(ItemStack::new(ItemId::Gun, 1));
+        inv.add_item(ItemStack::new(ItemId::Apple, 2));
+        inv.swap_slots(0, 1);
+        assert_eq!(inv.map[&0].item_id, ItemId::Apple);
+        assert_eq!(inv.map[&1].item_id, ItemId::Gun);
+        inv.swap_slots(1, 7);
+        assert!(!inv.map.contains_key(&1));
+        assert_eq!(inv.map[&7].item_id, ItemId::Gun);
+    }
+
+    #[test]
+    fn move_merges_the_same_item() {
+        let mut inv = Inventory::new();
+        inv.map.insert(0, ItemStack::new(ItemId::Apple, 10));
+        inv.map.insert(1, ItemStack::new(ItemId::Apple, 10));
+        inv.move_stack(0, 1);
+        assert_eq!(inv.map[&1].num, 16);
+        assert_eq!(inv.map[&0].num, 4);
+
+        inv.map.insert(2, ItemStack::new(ItemId::Sword, 1));
+        inv.move_stack(2, 0);
+        assert_eq!(inv.map[&0].item_id, ItemId::Sword);
+        assert_eq!(inv.map[&2].num, 4);
+    }
+
+    #[test]
+    fn split_puts_some_in_the_first_empty_slot() {
+        let mut inv = Inventory::new();
+        inv.add_item(ItemStack::new(ItemId::Sword, 1));
+        inv.add_item(ItemStack::new(ItemId::Ammo, 20));
+        assert_eq!(inv.split_stack(1, 5), Some(2));
+        assert_eq!(inv.map[&1].num, 15);
+        assert_eq!(inv.map[&2].num, 5);
+        assert_eq!(inv.split_stack(1, 15), None);
+        assert_eq!(inv.split_stack(1, 0), None);
+        assert_eq!(inv.split_stack(4, 1), None);
+    }
 
+    #[test]
+    fn split_needs_an_empty_slot() {
+        let mut inv = full_of_swords();
+        inv.map.insert(0, ItemStack::new(ItemId::Apple, 4));
+        assert_eq!(inv.split_stack(0, 2), None);
+        assert_eq!(inv.map[&0].num, 4);
     }
 }
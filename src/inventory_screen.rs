@@ -0,0 +1,333 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::PrimaryWindow;
+
+use crate::actions::{Action, ActionState, Menu, MenuOpen};
+use crate::hotbar::{SlotId, HOTBAR_SLOTS};
+use crate::inventory::{Inventory, ItemType, INVENTORY_SLOTS};
+use crate::pickup::DropItems;
+use crate::player::Player;
+
+pub struct InventoryScreenPlugin;
+
+const SLOT_SIZE: f32 = 64.0;
+const SLOT_GAP: f32 = 6.0;
+const SLOT: &str = "#333333";
+const SLOT_HOVER: &str = "#555555";
+const SLOT_DRAGGED: &str = "#884400";
+/// Border round the slots that are also on the hotbar
+const HOTBAR_BORDER: &str = "#aa8844";
+const BAG_BORDER: &str = "#666666";
+
+/// Every slot of the player's inventory, to rearrange
+#[derive(Component)]
+struct InventoryScreen;
+
+/// Holds the slots. Letting go of a dragged stack outside it drops it.
+#[derive(Component)]
+struct InventoryPanel;
+
+/// One inventory slot on the screen
+#[derive(Component)]
+struct ScreenSlot(u32);
+
+/// Says what's under the cursor
+#[derive(Component)]
+struct ItemDetails;
+
+/// Follows the cursor with whatever's being dragged
+#[derive(Component)]
+struct DragGhost;
+
+/// The slot an item is being dragged out of
+#[derive(Resource, Default)]
+struct Dragging(Option<u32>);
+
+impl Plugin for InventoryScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Dragging>();
+        app.add_systems(Update, (
+            toggle_screen,
+            drag_items,
+            show_slots,
+            show_details,
+            move_ghost
+        ).chain());
+    }
+}
+
+fn toggle_screen(
+    actions: Res<ActionState>,
+    mut menu: ResMut<MenuOpen>,
+    mut dragging: ResMut<Dragging>,
+    screens: Query<Entity, With<InventoryScreen>>,
+    mut commands: Commands,
+) {
+    if actions.just_pressed(Action::Inventory) {
+        match menu.0 {
+            None => menu.0 = Some(Menu::Inventory),
+            Some(Menu::Inventory) => menu.0 = None,
+            // Not over the top of another menu
+            Some(_) => {}
+        }
+    }
+
+    // Pause can shut it too
+    if menu.0 != Some(Menu::Inventory) {
+        if !screens.is_empty() {
+            for e in screens.iter() {
+                commands.entity(e).despawn_recursive();
+            }
+            dragging.0 = None;
+        }
+        return;
+    }
+    if !screens.is_empty() {
+        return;
+    }
+
+    commands.spawn((
+        Name::new("Inventory"),
+        InventoryScreen,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+    )).with_children(|p| {
+        p.spawn((
+            InventoryPanel,
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                padding: UiRect::all(Val::Px(16.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.9)),
+            RelativeCursorPosition::default(),
+        )).with_children(|p| {
+            p.spawn(Text::new("Inventory - drag to move, right click to split,\ndrag out to drop"));
+            p.spawn(Text::new("Hotbar"));
+            spawn_row(p, 0..HOTBAR_SLOTS, HOTBAR_BORDER);
+            p.spawn(Text::new("Bag"));
+            spawn_row(p, HOTBAR_SLOTS..INVENTORY_SLOTS, BAG_BORDER);
+            p.spawn((
+                ItemDetails,
+                Node {
+                    width: Val::Px((SLOT_SIZE + SLOT_GAP) * HOTBAR_SLOTS as f32),
+                    min_height: Val::Px(80.0),
+                    margin: UiRect::top(Val::Px(8.0)),
+                    ..default()
+                },
+                Text::new(""),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+            ));
+        });
+        p.spawn((
+            DragGhost,
+            Node {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            Text::new(""),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+            Visibility::Hidden,
+        ));
+    });
+}
+
+fn spawn_row(p: &mut ChildBuilder, slots: std::ops::Range<u32>, border: &str) {
+    p.spawn(Node {
+        width: Val::Px((SLOT_SIZE + SLOT_GAP) * HOTBAR_SLOTS as f32),
+        flex_wrap: FlexWrap::Wrap,
+        column_gap: Val::Px(SLOT_GAP),
+        row_gap: Val::Px(SLOT_GAP),
+        ..default()
+    }).with_children(|p| {
+        for i in slots {
+            p.spawn((
+                Name::new(format!("slot{}", i)),
+                ScreenSlot(i),
+                Node {
+                    width: Val::Px(SLOT_SIZE),
+                    height: Val::Px(SLOT_SIZE),
+                    border: UiRect::all(Val::Px(2.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                BackgroundColor(Srgba::hex(SLOT).unwrap().into()),
+                BorderColor(Srgba::hex(border).unwrap().into()),
+                RelativeCursorPosition::default(),
+            )).with_child((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+        }
+    });
+}
+
+/// The inventory slot under the cursor, on this screen or the hotbar
+fn slot_under_cursor(
+    screen_slots: &Query<(&ScreenSlot, &RelativeCursorPosition)>,
+    hotbar_slots: &Query<(&SlotId, &RelativeCursorPosition)>,
+) -> Option<u32> {
+    screen_slots
+        .iter()
+        .find(|(_, cursor)| cursor.mouse_over())
+        .map(|(slot, _)| slot.0)
+        .or_else(|| {
+            hotbar_slots
+                .iter()
+                .find(|(_, cursor)| cursor.mouse_over())
+                .map(|(slot, _)| slot.0)
+        })
+}
+
+/// Left drag moves a stack, merging or swapping with what's there, or
+/// drops it on the ground if let go of away from the slots. Right click
+/// splits a stack in half.
+#[allow(clippy::too_many_arguments)]
+fn drag_items(
+    buttons: Res<ButtonInput<MouseButton>>,
+    menu: Res<MenuOpen>,
+    mut dragging: ResMut<Dragging>,
+    screen_slots: Query<(&ScreenSlot, &RelativeCursorPosition)>,
+    hotbar_slots: Query<(&SlotId, &RelativeCursorPosition)>,
+    panels: Query<&RelativeCursorPosition, With<InventoryPanel>>,
+    mut player: Query<&mut Inventory, With<Player>>,
+    mut commands: Commands,
+) {
+    if menu.0 != Some(Menu::Inventory) {
+        return;
+    }
+    let Ok(mut inv) = player.get_single_mut() else {
+        return;
+    };
+    let under = slot_under_cursor(&screen_slots, &hotbar_slots);
+
+    if buttons.just_pressed(MouseButton::Left) {
+        dragging.0 = under.filter(|slot| inv.map.contains_key(slot));
+    }
+    if buttons.just_released(MouseButton::Left) {
+        match (dragging.0.take(), under) {
+            (Some(from), Some(to)) if from != to => {
+                info!("moved slot {} to {}", from, to);
+                inv.move_stack(from, to);
+            }
+            (Some(from), None) if !panels.iter().any(|p| p.mouse_over()) => {
+                let num = inv.map.get(&from).map_or(0, |s| s.num);
+                commands.trigger(DropItems { slot: from, num });
+            }
+            _ => {}
+        }
+    }
+    if buttons.just_pressed(MouseButton::Right) && dragging.0.is_none() {
+        let half = under.and_then(|slot| inv.map.get(&slot)).map_or(0, |s| s.num / 2);
+        if let (Some(slot), true) = (under, half > 0) {
+            if let Some(to) = inv.split_stack(slot, half) {
+                info!("split {} off slot {} into {}", half, slot, to);
+            }
+        }
+    }
+}
+
+fn show_slots(
+    dragging: Res<Dragging>,
+    player: Query<&Inventory, With<Player>>,
+    mut slots: Query<(&ScreenSlot, &RelativeCursorPosition, &Children, &mut BackgroundColor)>,
+    mut texts: Query<&mut Text>,
+) {
+    let Ok(inv) = player.get_single() else {
+        return;
+    };
+    for (slot, cursor, children, mut bg) in slots.iter_mut() {
+        let color = if dragging.0 == Some(slot.0) {
+            SLOT_DRAGGED
+        } else if cursor.mouse_over() {
+            SLOT_HOVER
+        } else {
+            SLOT
+        };
+        bg.set_if_neq(BackgroundColor(Srgba::hex(color).unwrap().into()));
+
+        let label = inv.map.get(&slot.0).map_or(String::new(), |s| s.label());
+        if let Some(mut text) = children.first().and_then(|c| texts.get_mut(*c).ok()) {
+            if **text != label {
+                **text = label;
+            }
+        }
+    }
+}
+
+/// What's in the slot under the cursor
+fn show_details(
+    player: Query<&Inventory, With<Player>>,
+    screen_slots: Query<(&ScreenSlot, &RelativeCursorPosition)>,
+    hotbar_slots: Query<(&SlotId, &RelativeCursorPosition)>,
+    mut details: Query<&mut Text, With<ItemDetails>>,
+) {
+    let (Ok(inv), Ok(mut text)) = (player.get_single(), details.get_single_mut()) else {
+        return;
+    };
+    let stack = slot_under_cursor(&screen_slots, &hotbar_slots)
+        .and_then(|slot| inv.map.get(&slot).map(|s| (slot, s)));
+    let detail = match stack {
+        Some((slot, stack)) => {
+            let kind = match stack.item_type {
+                ItemType::Generic => "Item".to_string(),
+                ItemType::BodyPart(part) => format!("Body part, a {:?}", part),
+                ItemType::Tool(_) => "Tool, used from the hotbar".to_string(),
+            };
+            let place = if slot < HOTBAR_SLOTS { "hotbar" } else { "bag" };
+            format!(
+                "{:?}\n{}\n{} of {} a slot holds\nSlot {} in the {}",
+                stack.item_id, kind, stack.num, stack.item_id.max_stack(), slot, place
+            )
+        }
+        None => String::new(),
+    };
+    if **text != detail {
+        **text = detail;
+    }
+}
+
+fn move_ghost(
+    dragging: Res<Dragging>,
+    player: Query<&Inventory, With<Player>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut ghosts: Query<(&mut Node, &mut Text, &mut Visibility), With<DragGhost>>,
+) {
+    let Ok((mut node, mut text, mut visibility)) = ghosts.get_single_mut() else {
+        return;
+    };
+    let stack = dragging.0.and_then(|slot| player.get_single().ok()?.map.get(&slot).copied());
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let (Some(stack), Some(cursor)) = (stack, cursor) else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    visibility.set_if_neq(Visibility::Inherited);
+    node.left = Val::Px(cursor.x + 8.0);
+    node.top = Val::Px(cursor.y + 8.0);
+    let label = stack.label();
+    if **text != label {
+        **text = label;
+    }
+}
@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+
+use crate::inventory::{BodyPartType, ItemId, ItemStack};
+use crate::crowd::{CrowdSet, SimBudget};
+use crate::person::{BodyRoot, Health, KillPerson, Person};
+use crate::pickup::SpawnItem;
+use crate::status::{ApplyStatus, StatusEffect, StatusKind};
+
+pub struct LimbPlugin;
+
+/// Seconds a stump bleeds for before it closes up
+const STUMP_BLEED_SECS: f32 = 20.0;
+
+/// A body part scene attached to a body, and what kind of part it is
+#[derive(Debug, Component)]
+pub struct BodyPart(pub BodyPartType);
+
+#[derive(Debug, Component)]
+pub struct LimbHealth(pub f32);
+
+/// How many of each part a body has, counted down its whole hierarchy
+#[derive(Debug, Clone, Default, PartialEq, Component)]
+pub struct BodyComposition {
+    pub heads: u32,
+    pub torsos: u32,
+    pub legs: u32,
+    pub arms: u32,
+}
+
+impl BodyComposition {
+    pub fn count(&self, part: BodyPartType) -> u32 {
+        match part {
+            BodyPartType::Head => self.heads,
+            BodyPartType::Torso => self.torsos,
+            BodyPartType::Leg => self.legs,
+            BodyPartType::Arm => self.arms,
+        }
+    }
+
+    fn add(&mut self, part: BodyPartType) {
+        match part {
+            BodyPartType::Head => self.heads += 1,
+            BodyPartType::Torso => self.torsos += 1,
+            BodyPartType::Leg => self.legs += 1,
+            BodyPartType::Arm => self.arms += 1,
+        }
+    }
+}
+
+/// What a person can do with the limbs they've got
+#[derive(Debug, Component)]
+pub struct Capabilities {
+    pub can_walk: bool,
+    pub can_carry: bool,
+    /// Multiplier on walking speed
+    pub speed: f32,
+    /// How far away they notice things
+    pub perception: f32,
+    pub carry_capacity: f32,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::from_composition(&BodyComposition {
+            heads: 1,
+            torsos: 1,
+            legs: 2,
+            arms: 2
+        })
+    }
+}
+
+impl Capabilities {
+    pub fn from_composition(body: &BodyComposition) -> Self {
+        // One leg hops, extra legs scuttle
+        let speed = match body.legs {
+            0 => 0.0,
+            1 => 0.35,
+            n => (1.0 + 0.25 * (n - 2) as f32).min(2.0),
+        };
+        Self {
+            can_walk: body.legs > 0,
+            can_carry: body.arms > 0,
+            speed,
+            perception: 10.0 * body.heads as f32,
+            carry_capacity: 15.0 * body.arms as f32,
+        }
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct SeverLimb;
+
+/// How much an item hurts the limb it lands on, and the body it's attached to
+pub struct LimbDamage {
+    pub limb: f32,
+    pub body: f32,
+}
+
+impl BodyPartType {
+    pub fn max_health(&self) -> f32 {
+        match *self {
+            Self::Head => 50.0,
+            Self::Torso => 100.0,
+            Self::Leg => 60.0,
+            Self::Arm => 40.0,
+        }
+    }
+
+    /// Health per second lost when this part is cut off
+    pub fn bleed_rate(&self) -> f32 {
+        match *self {
+            Self::Head => 0.0,
+            Self::Torso => 0.0,
+            Self::Leg => 4.0,
+            Self::Arm => 2.5,
+        }
+    }
+}
+
+/// What a hit does. Cutting a limb off takes two cleaver hits for an
+/// arm or head, and three for a leg.
+pub fn limb_damage(item_id: ItemId) -> LimbDamage {
+    match item_id {
+        ItemId::Sword => LimbDamage { limb: 25.0, body: 10.0 },
+        ItemId::Gun => LimbDamage { limb: 45.0, body: 35.0 },
+        _ => LimbDamage { limb: 15.0, body: 25.0 },
+    }
+}
+
+impl Plugin for LimbPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            compose_bodies,
+            derive_capabilities
+        ).chain().after(CrowdSet));
+        app.add_observer(sever_limb);
+    }
+}
+
+fn compose_bodies(
+    mut roots: Query<(Entity, &mut BodyComposition, Option<&SimBudget>), With<BodyRoot>>,
+    children: Query<&Children>,
+    parts: Query<&BodyPart>,
+) {
+    for (root, mut composition, budget) in roots.iter_mut() {
+        if budget.is_some_and(|b| !b.ready) {
+            continue;
+        }
+        let mut next = BodyComposition::default();
+        for e in std::iter::once(root).chain(children.iter_descendants(root)) {
+            if let Ok(part) = parts.get(e) {
+                next.add(part.0);
+            }
+        }
+        composition.set_if_neq(next);
+    }
+}
+
+fn derive_capabilities(
+    mut q: Query<(Entity, &BodyComposition, &mut Capabilities, &Health), (With<Person>, Changed<BodyComposition>)>,
+    mut commands: Commands,
+) {
+    for (e, composition, mut caps, health) in q.iter_mut() {
+        *caps = Capabilities::from_composition(composition);
+        if composition.heads == 0 && health.0 > 0.0 {
+            info!("lost their head");
+            commands.trigger_targets(KillPerson::default(), e);
+        }
+    }
+}
+
+fn sever_limb(
+    trigger: Trigger<SeverLimb>,
+    parent_q: Query<&Parent>,
+    parts: Query<(&BodyPart, &GlobalTransform)>,
+    persons: Query<(), With<Person>>,
+    mut commands: Commands,
+) {
+    let limb = trigger.entity();
+    let Ok((BodyPart(part_type), limb_t)) = parts.get(limb) else {
+        return;
+    };
+    let root = parent_q.root_ancestor(limb);
+    if !persons.contains(root) {
+        return;
+    }
+
+    info!("severed {:?}", part_type);
+
+    if *part_type == BodyPartType::Torso {
+        commands.trigger_targets(KillPerson::default(), root);
+        return;
+    }
+
+    // What's left gets counted up again next frame, and capabilities follow
+    let rate = part_type.bleed_rate();
+    if rate > 0.0 {
+        commands.trigger_targets(ApplyStatus(StatusEffect::timed(StatusKind::Bleeding, rate, STUMP_BLEED_SECS)), root);
+    }
+
+    // Falls off as something to pick up
+    commands.trigger(SpawnItem {
+        stack: ItemStack::new(ItemId::from_body_part(*part_type), 1),
+        pos: limb_t.translation(),
+        velocity: Vec3::Y * 2.0,
+    });
+    commands.entity(limb).remove_parent();
+    commands.entity(limb).despawn_recursive();
+}
@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use bevy::animation::{animated_field, AnimationTarget, AnimationTargetId};
+use std::f32::consts::*;
+
+use crate::gltf_tags::{GltfTagAppExt, NamePattern};
+use crate::inventory::BodyPartType;
+use crate::limb::BodyPart;
+use crate::person::{person_of, Carried, Knockback, Person};
+
+pub struct LocomotionPlugin;
+
+/// Distance covered in one cycle of each gait, so the clip playback
+/// speed can be matched to how fast the body is actually moving.
+const WALK_STRIDE: f32 = 0.6;
+const RUN_STRIDE: f32 = 1.4;
+
+/// Speeds where the blend is fully walking, and fully running
+const WALK_SPEED: f32 = 0.15;
+const RUN_SPEED: f32 = 2.0;
+
+/// How quickly blend weights chase their target
+const BLEND_RATE: f32 = 8.0;
+
+const ANIMATED_JOINTS: [&str; 6] = ["shoulder", "forearm", "hand", "LegLowerBone", "HeadBone", "SerheadBone"];
+
+#[derive(Resource)]
+pub struct PersonAnimations {
+    pub graph: Handle<AnimationGraph>,
+    pub idle: AnimationNodeIndex,
+    pub walk: AnimationNodeIndex,
+    pub run: AnimationNodeIndex,
+    pub hit: AnimationNodeIndex,
+    pub carry: AnimationNodeIndex,
+}
+
+impl PersonAnimations {
+    /// An animation player with every state running, blended to idle
+    pub fn player(&self) -> AnimationPlayer {
+        let mut player = AnimationPlayer::default();
+        for node in [self.idle, self.walk, self.run, self.hit, self.carry] {
+            player.play(node).repeat().set_weight(0.0);
+        }
+        if let Some(idle) = player.animation_mut(self.idle) {
+            idle.set_weight(1.0);
+        }
+        player
+    }
+}
+
+/// Measured ground speed of a body, used to drive the gait
+#[derive(Component, Default)]
+pub struct Locomotion {
+    prev: Option<Vec3>,
+    pub speed: f32,
+}
+
+impl Plugin for LocomotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, setup);
+        app.add_systems(Update, (
+            measure_locomotion,
+            blend_locomotion
+        ).chain());
+
+        // Joints on a person are driven by their animation graph
+        for joint in ANIMATED_JOINTS {
+            app.register_gltf_tag(NamePattern::exact(joint), |e, ctx| {
+                let joint = ctx.name.to_string();
+                e.queue(move |entity: Entity, world: &mut World| {
+                    let Some(player) = person_of(world, entity) else {
+                        return;
+                    };
+                    let Some(id) = part_of(world, entity)
+                        .and_then(|(part, part_name)| joint_target_id(part, part_name, &joint))
+                    else {
+                        return;
+                    };
+                    world.entity_mut(entity).insert(AnimationTarget { id, player });
+                });
+            });
+        }
+    }
+}
+
+/// The body part scene `entity` is inside, and its name
+fn part_of(world: &World, entity: Entity) -> Option<(&BodyPart, &str)> {
+    let mut e = entity;
+    while let Some(parent) = world.get::<Parent>(e) {
+        e = parent.get();
+        if let (Some(part), Some(name)) = (world.get::<BodyPart>(e), world.get::<Name>(e)) {
+            return Some((part, name.as_str()));
+        }
+    }
+    None
+}
+
+/// Animation target for a joint in a person's body part, if the
+/// controller animates it. Sides are shared by every person, so one
+/// set of clips drives the whole crowd.
+pub fn joint_target_id(part: &BodyPart, part_name: &str, joint: &str) -> Option<AnimationTargetId> {
+    if !ANIMATED_JOINTS.contains(&joint) {
+        return None;
+    }
+    let side = match (part.0, part_name) {
+        (BodyPartType::Head, _) => "centre",
+        (_, "Arm2" | "leg2") => "right",
+        _ => "left"
+    };
+    Some(target_id(side, joint))
+}
+
+fn target_id(side: &str, joint: &str) -> AnimationTargetId {
+    AnimationTargetId::from_names([Name::new(side.to_string()), Name::new(joint.to_string())].iter())
+}
+
+/// One looping rotation about `axis`: `amp * sin(phase)` over a one second cycle
+fn swing(clip: &mut AnimationClip, side: &str, joint: &str, axis: Vec3, amp: f32, offset: f32, phase: f32) {
+    const SAMPLES: usize = 16;
+    let keys = (0..=SAMPLES).map(|i| {
+        let t = i as f32 / SAMPLES as f32;
+        let angle = offset + amp * (t * TAU + phase).sin();
+        (t, Quat::from_axis_angle(axis, angle))
+    });
+    let Ok(curve) = AnimatableKeyframeCurve::new(keys) else {
+        return;
+    };
+    clip.add_curve_to_target(
+        target_id(side, joint),
+        AnimatableCurve::new(animated_field!(Transform::rotation), curve)
+    );
+}
+
+/// Legs and arms swing in opposite phase, left and right opposite each other
+fn gait_clip(legs: f32, arms: f32, forearms: f32, head: f32) -> AnimationClip {
+    let mut clip = AnimationClip::default();
+    for (side, phase) in [("left", 0.0), ("right", PI)] {
+        swing(&mut clip, side, "LegLowerBone", Vec3::X, legs, 0.0, phase);
+        swing(&mut clip, side, "shoulder", Vec3::Y, arms, 0.0, phase + PI);
+        swing(&mut clip, side, "forearm", Vec3::Y, forearms * 0.5, forearms, phase + PI);
+        swing(&mut clip, side, "hand", Vec3::Z, arms * 0.3, 0.0, phase);
+    }
+    swing(&mut clip, "centre", "HeadBone", Vec3::X, head, 0.0, 0.0);
+    swing(&mut clip, "centre", "SerheadBone", Vec3::X, head, 0.0, 0.0);
+    clip
+}
+
+/// A held pose with a little tremble
+fn pose_clip(legs: f32, shoulders: f32, forearms: f32, head: f32) -> AnimationClip {
+    let mut clip = AnimationClip::default();
+    for (side, phase) in [("left", 0.0), ("right", PI)] {
+        swing(&mut clip, side, "LegLowerBone", Vec3::X, 0.05, legs, phase);
+        swing(&mut clip, side, "shoulder", Vec3::Z, 0.05, shoulders, phase);
+        swing(&mut clip, side, "forearm", Vec3::Z, 0.05, forearms, phase);
+    }
+    swing(&mut clip, "centre", "HeadBone", Vec3::X, 0.05, head, 0.0);
+    swing(&mut clip, "centre", "SerheadBone", Vec3::X, 0.05, head, 0.0);
+    clip
+}
+
+fn setup(
+    mut commands: Commands,
+    mut clips: ResMut<Assets<AnimationClip>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+) {
+    let mut graph = AnimationGraph::new();
+    let root = graph.root;
+
+    let idle = graph.add_clip(clips.add(gait_clip(0.0, 0.06, 0.1, 0.04)), 1.0, root);
+    let walk = graph.add_clip(clips.add(gait_clip(0.45, 0.35, 0.2, 0.08)), 1.0, root);
+    let run = graph.add_clip(clips.add(gait_clip(0.9, 0.7, 0.9, 0.15)), 1.0, root);
+    let hit = graph.add_clip(clips.add(pose_clip(0.3, 0.9, 0.6, -0.6)), 1.0, root);
+    let carry = graph.add_clip(clips.add(pose_clip(0.2, -0.4, -0.3, 0.5)), 1.0, root);
+
+    commands.insert_resource(PersonAnimations {
+        graph: graphs.add(graph),
+        idle,
+        walk,
+        run,
+        hit,
+        carry
+    });
+}
+
+fn measure_locomotion(
+    time: Res<Time>,
+    mut q: Query<(&GlobalTransform, &mut Locomotion)>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+    for (t, mut loco) in q.iter_mut() {
+        let pos = t.translation() * Vec3::new(1.0, 0.0, 1.0);
+        let speed = loco.prev.map_or(0.0, |prev| pos.distance(prev) / dt);
+        // Smooth it out, frame times are noisy
+        loco.speed = loco.speed.lerp(speed, (dt * 10.0).min(1.0));
+        loco.prev = Some(pos);
+    }
+}
+
+fn blend_locomotion(
+    time: Res<Time>,
+    anims: Res<PersonAnimations>,
+    mut q: Query<(&Locomotion, &mut AnimationPlayer, Has<Knockback>, Has<Carried>), With<Person>>,
+) {
+    let k = (time.delta_secs() * BLEND_RATE).min(1.0);
+
+    for (loco, mut player, knocked, carried) in q.iter_mut() {
+        let v = loco.speed;
+        let moving = (v / WALK_SPEED).clamp(0.0, 1.0);
+        let running = ((v - WALK_SPEED) / (RUN_SPEED - WALK_SPEED)).clamp(0.0, 1.0);
+
+        let mut targets = [
+            (anims.idle, 1.0 - moving),
+            (anims.walk, moving * (1.0 - running)),
+            (anims.run, moving * running),
+            (anims.hit, 0.0),
+            (anims.carry, 0.0),
+        ];
+        if carried {
+            targets.iter_mut().for_each(|t| t.1 = 0.0);
+            targets[4].1 = 1.0;
+        } else if knocked {
+            targets.iter_mut().for_each(|t| t.1 *= 0.3);
+            targets[3].1 = 0.7;
+        }
+
+        for (node, target) in targets {
+            if let Some(anim) = player.animation_mut(node) {
+                let w = anim.weight();
+                anim.set_weight(w + (target - w) * k);
+            }
+        }
+
+        // One cycle covers a stride, so play back at speed / stride.
+        // Walk and run share a phase so blending between them doesn't scissor the legs.
+        let stride = WALK_STRIDE.lerp(RUN_STRIDE, running);
+        let rate = v / stride;
+        let phase = player.animation(anims.walk).map_or(0.0, |a| a.seek_time());
+        if let Some(walk) = player.animation_mut(anims.walk) {
+            walk.set_speed(rate);
+        }
+        if let Some(run) = player.animation_mut(anims.run) {
+            run.set_speed(rate).seek_to(phase);
+        }
+    }
+}
@@ -1,4 +1,8 @@
+// Bevy queries get long, and that's fine
+#![allow(clippy::type_complexity)]
+
 mod game;
+pub mod gltf_tags;
 pub mod terrain;
 pub mod nim;
 pub mod person;
@@ -9,6 +13,28 @@ pub mod ui;
 pub mod bob;
 pub mod inventory;
 pub mod hotbar;
+pub mod inventory_screen;
+pub mod pickup;
+pub mod limb;
+pub mod locomotion;
+pub mod ragdoll;
+pub mod ik;
+pub mod socket;
+pub mod physics;
+pub mod corpse;
+pub mod appearance;
+pub mod crowd;
+pub mod status;
+pub mod controller;
+pub mod carry;
+pub mod tool;
+pub mod gun;
+pub mod melee;
+pub mod actions;
+pub mod controls;
+pub mod camera;
+pub mod vitals;
+pub mod interact;
 
 use bevy::prelude::*;
 
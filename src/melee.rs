@@ -0,0 +1,285 @@
+use bevy::prelude::*;
+use std::f32::consts::*;
+
+use crate::inventory::ItemId;
+use crate::person::{Carryable, Carried, HitBodyPart, Knockback, Person, Pickable};
+use crate::physics::{impulse, Mass};
+use crate::player::{hand, Player};
+use crate::interact::InteractLayers;
+use crate::tool::{Tool, ToolAppExt, ToolTarget, ToolUse, ToolViz, Viewmodel};
+
+pub struct MeleePlugin;
+
+/// Where the swing is seen from, relative to the swinger
+const EYE: Vec3 = Vec3::new(0.0, 1.5, 0.0);
+/// Rays cast per frame across the part of the arc swept that frame
+const SWEEP_RAYS: usize = 4;
+
+/// How a melee weapon swings
+#[derive(Debug, Clone, Copy)]
+pub struct Melee {
+    /// What the hit counts as, for damage and status effects
+    pub item_id: ItemId,
+    pub power: f32,
+    pub reach: f32,
+    /// Radians swept side to side in front of the swinger
+    pub arc: f32,
+    /// Seconds pulling back, cutting, and recovering
+    pub windup: f32,
+    pub active: f32,
+    pub recover: f32,
+}
+
+impl Melee {
+    fn duration(&self) -> f32 {
+        self.windup + self.active + self.recover
+    }
+
+    /// Angle across the arc `t` seconds into the swing, and whether
+    /// it can hit anything yet
+    fn angle(&self, t: f32) -> (f32, bool) {
+        let half = self.arc / 2.0;
+        if t < self.windup {
+            (half * t / self.windup, false)
+        } else if t < self.windup + self.active {
+            let s = (t - self.windup) / self.active;
+            (half - self.arc * s, true)
+        } else {
+            let s = ((t - self.windup - self.active) / self.recover).min(1.0);
+            (-half * (1.0 - s), false)
+        }
+    }
+}
+
+pub const FIST: Melee = Melee {
+    item_id: ItemId::Fist,
+    power: 20.0,
+    reach: 2.5,
+    arc: 0.6,
+    windup: 0.08,
+    active: 0.1,
+    recover: 0.2,
+};
+
+pub const CLEAVER: Melee = Melee {
+    item_id: ItemId::Sword,
+    power: 5.0,
+    reach: 3.0,
+    arc: 1.6,
+    windup: 0.15,
+    active: 0.15,
+    recover: 0.25,
+};
+
+/// Seconds to wind the cleaver up fully
+const CLEAVER_CHARGE: f32 = 0.8;
+/// How many times harder a fully wound up chop lands
+const CLEAVER_WINDUP: f32 = 3.0;
+
+/// Start a swing
+#[derive(Debug, Event)]
+pub struct Swing(pub Melee);
+
+/// Mid swing
+#[derive(Component)]
+pub struct Swinging {
+    pub melee: Melee,
+    pub t: f32,
+    /// Things already hit this swing, which can't be hit again
+    hit: Vec<Entity>,
+    /// Where the viewmodel sits when it's not swinging
+    rest: Option<(Entity, Transform)>,
+}
+
+struct FistTool;
+
+impl Tool for FistTool {
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        commands.trigger_targets(Swing(FIST), ctx.user);
+    }
+
+    fn cooldown(&self) -> f32 {
+        FIST.duration()
+    }
+
+    fn reach(&self) -> f32 {
+        FIST.reach
+    }
+
+    fn verb(&self, _target: &ToolTarget) -> Option<String> {
+        Some("punch".to_string())
+    }
+
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::PERSON | InteractLayers::BODY_PART | InteractLayers::PROP
+    }
+
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        Some(hand())
+    }
+}
+
+struct CleaverTool;
+
+impl Tool for CleaverTool {
+    /// Holding on winds up a harder chop
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        let power = CLEAVER.power * (1.0 + ctx.charge * (CLEAVER_WINDUP - 1.0));
+        commands.trigger_targets(Swing(Melee { power, ..CLEAVER }), ctx.user);
+    }
+
+    fn charge_time(&self) -> Option<f32> {
+        Some(CLEAVER_CHARGE)
+    }
+
+    fn cooldown(&self) -> f32 {
+        CLEAVER.duration()
+    }
+
+    fn reach(&self) -> f32 {
+        CLEAVER.reach
+    }
+
+    fn verb(&self, _target: &ToolTarget) -> Option<String> {
+        Some("cut".to_string())
+    }
+
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::PERSON | InteractLayers::BODY_PART | InteractLayers::PROP
+    }
+
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        Some(Viewmodel {
+            scene: "cleaver.glb",
+            transform: Transform::from_xyz(0.65, 0.7, -1.75)
+                .with_rotation(Quat::from_euler(EulerRot::YXZ, -PI/2.5, 0., -PI / 2.))
+                .with_scale(Vec3::splat(2.0)),
+        })
+    }
+}
+
+impl Plugin for MeleePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_tool(ItemId::Fist, FistTool);
+        app.register_tool(ItemId::Sword, CleaverTool);
+        app.add_systems(Update, (
+            swing,
+            animate_swing
+        ).chain());
+        app.add_observer(start_swing);
+    }
+}
+
+fn start_swing(
+    trigger: Trigger<Swing>,
+    swinging: Query<(), With<Swinging>>,
+    children: Query<&Children>,
+    viewmodels: Query<&Transform, With<ToolViz>>,
+    mut commands: Commands,
+) {
+    let e = trigger.entity();
+    if swinging.contains(e) {
+        return;
+    }
+    let rest = children
+        .get(e)
+        .ok()
+        .and_then(|c| c.iter().find_map(|c| viewmodels.get(*c).ok().map(|t| (*c, *t))));
+    commands.entity(e).insert(Swinging {
+        melee: trigger.event().0,
+        t: 0.0,
+        hit: vec![],
+        rest,
+    });
+}
+
+/// Sweep rays across the bit of arc covered this frame, while the
+/// swing is in its hit window
+#[allow(clippy::too_many_arguments)]
+fn swing(
+    time: Res<Time>,
+    mut ray_cast: MeshRayCast,
+    mut swingers: Query<(Entity, &GlobalTransform, &mut Swinging)>,
+    pickable: Query<(), With<Pickable>>,
+    players: Query<(), With<Player>>,
+    parent_q: Query<&Parent>,
+    persons: Query<(), With<Person>>,
+    props: Query<Option<&Mass>, (With<Carryable>, Without<Person>, Without<Carried>)>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_secs();
+    for (e, gt, mut swinging) in swingers.iter_mut() {
+        let melee = swinging.melee;
+        let (from, _) = melee.angle(swinging.t);
+        swinging.t += dt;
+        let (to, active) = melee.angle(swinging.t);
+
+        if swinging.t >= melee.duration() {
+            commands.entity(e).remove::<Swinging>();
+            continue;
+        }
+        if !active {
+            continue;
+        }
+
+        let eye = gt.transform_point(EYE);
+        let filter = |entity: Entity| {
+            pickable.contains(entity)
+                && !parent_q.iter_ancestors(entity).any(|a| a == e || players.contains(a))
+        };
+        let settings = RayCastSettings::default()
+            .with_filter(&filter);
+
+        for i in 0..=SWEEP_RAYS {
+            let angle = from + (to - from) * i as f32 / SWEEP_RAYS as f32;
+            let dir = Quat::from_axis_angle(*gt.up(), angle) * *gt.forward();
+            let Ok(dir) = Dir3::new(dir) else {
+                continue;
+            };
+            let Some((mesh, _)) = ray_cast
+                .cast_ray(Ray3d::new(eye, dir), &settings)
+                .first()
+                .filter(|(_, hit)| hit.distance <= melee.reach)
+                .map(|(mesh, hit)| (*mesh, hit.point))
+            else {
+                continue;
+            };
+
+            let root = parent_q.root_ancestor(mesh);
+            if swinging.hit.contains(&root) {
+                continue;
+            }
+            swinging.hit.push(root);
+
+            if persons.contains(root) {
+                commands.trigger_targets(
+                    HitBodyPart { item_id: melee.item_id, dir, power: melee.power, by: Some(e) },
+                    mesh
+                );
+            } else if let Ok(mass) = props.get(root) {
+                let velocity = impulse(melee.power, *dir, mass);
+                commands.entity(root).insert(Knockback { velocity, grounded: false });
+            }
+        }
+    }
+}
+
+/// Swing the viewmodel round the eye, following the arc
+fn animate_swing(
+    swingers: Query<&Swinging>,
+    mut viewmodels: Query<&mut Transform, With<ToolViz>>,
+) {
+    for swinging in swingers.iter() {
+        let Some((vm, rest)) = swinging.rest else {
+            continue;
+        };
+        let Ok(mut t) = viewmodels.get_mut(vm) else {
+            continue;
+        };
+        let done = swinging.t >= swinging.melee.duration();
+        let (angle, _) = swinging.melee.angle(swinging.t);
+        let turn = if done { Quat::IDENTITY } else { Quat::from_rotation_y(angle) };
+        t.translation = EYE + turn * (rest.translation - EYE);
+        t.rotation = turn * rest.rotation;
+    }
+}
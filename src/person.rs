@@ -1,13 +1,30 @@
 use bevy::prelude::*;
+use bevy::animation::AnimationTarget;
 use std::f32::consts::*;
 
 use crate::game::Timey;
 use crate::bob::Bob;
-use crate::inventory::ItemId;
-use crate::townsfolk::LookingForWork;
+use crate::inventory::{BodyPartType, ItemId};
+use crate::locomotion::{Locomotion, PersonAnimations};
+use crate::limb::{limb_damage, BodyComposition, BodyPart, Capabilities, LimbHealth, SeverLimb};
+use crate::ragdoll::Ragdoll;
+use crate::socket::AttachPart;
+use crate::physics::{impulse, to_parent_space, Mass, FRICTION, GRAVITY};
+use crate::terrain::Terrain;
+use crate::controller::ground_below;
+use crate::gltf_tags::{GltfTagAppExt, NamePattern};
+use crate::townsfolk::{Hostile, LookingForWork, TownsfolkTask, TownsfolkTaskType};
+use crate::corpse::Corpse;
+use crate::appearance::{Appearance, AppearanceMaterials};
+use crate::crowd::{CrowdSet, LodLevel, SimBudget};
+use crate::interact::InteractLayers;
+use crate::status::{item_statuses, ApplyStatus, StatusEffects};
 
 pub struct PersonPlugin;
 
+/// How far above something flying the ground is looked for from
+const GROUND_LOOK: f32 = 0.5;
+
 #[derive(Debug, Component)]
 pub struct Health(pub f32);
 
@@ -15,12 +32,20 @@ pub struct Health(pub f32);
 pub struct SpawnPerson {
     pub pos: Vec3,
     pub speed: f32,
-    pub normal: Vec3
+    pub normal: Vec3,
+    /// Everything about how they look comes from this
+    pub seed: u64
 }
 
-#[derive(Debug, Event)]
-pub struct KillPerson;
+/// Kill a person, falling the way the killing blow pushed them
+#[derive(Debug, Event, Default)]
+pub struct KillPerson {
+    pub dir: Vec3,
+    pub power: f32
+}
 
+/// Spawn a part. When targeted at a mesh, `pos` and `normal` are in
+/// that mesh's local space and the part is attached to it.
 #[derive(Debug, Event)]
 pub struct SpawnBodyPart {
     pub pos: Vec3,
@@ -35,16 +60,20 @@ pub struct GltfBodyPart;
 pub struct HitBodyPart {
     pub item_id: ItemId,
     pub dir: Dir3,
-    pub power: f32
+    pub power: f32,
+    /// Who landed it, if anyone
+    pub by: Option<Entity>
 }
 
+/// Flying or sliding from a hit, until friction stops us on the ground
 #[derive(Component)]
 pub struct Knockback {
-    pub dir: Vec3,
-    pub duration: Timer
+    pub velocity: Vec3,
+    pub grounded: bool
 }
 
 #[derive(Component)]
+#[require(BodyComposition)]
 pub struct BodyRoot;
 
 
@@ -63,17 +92,58 @@ pub struct Pickable;
 #[derive(Component)]
 pub struct Carryable;
 
+/// Being held by the player
 #[derive(Component)]
-struct Speed(f32);
+pub struct Carried;
+
+#[derive(Component)]
+pub struct Speed(pub f32);
+
+/// The person `entity` is somewhere inside, straight from the world, for
+/// GLTF tags that need to know who a node belongs to
+pub fn person_of(world: &World, entity: Entity) -> Option<Entity> {
+    let mut e = entity;
+    while let Some(parent) = world.get::<Parent>(e) {
+        e = parent.get();
+        if world.get::<Person>(e).is_some() {
+            return Some(e);
+        }
+    }
+    None
+}
 
 impl Plugin for PersonPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, (
-            move_person,
+            move_person.after(CrowdSet),
             animate_joints,
             animate_joint_cycle,
             apply_knockback
         ));
+        app.register_gltf_tag(NamePattern::exact("forearm"), |e, _| {
+            e.insert((Jointy, Timey(0.0)));
+        });
+        app.register_gltf_tag(NamePattern::exact("shoulder"), |e, _| {
+            e.insert((Jointy, Timey(10.0)));
+        });
+        app.register_gltf_tag(NamePattern::exact("hand"), |e, _| {
+            e.insert((Jointy, Timey(3.0)));
+        });
+        app.register_gltf_tag(NamePattern::suffix("Mesh"), |e, _| {
+            e.insert(Pickable);
+        });
+        app.register_gltf_tag(NamePattern::exact("HeadBone"), |e, _| {
+            e.insert((JointCycle, Timey(3.0), Pickable));
+        });
+        app.register_gltf_tag(NamePattern::exact("SerheadBone"), |e, _| {
+            e.insert((Jointy, Timey(3.0), Pickable));
+        });
+        app.register_gltf_tag(NamePattern::exact("LegLowerBone"), |e, ctx| {
+            if ctx.offset > 0.0 {
+                e.insert((JointCycle, Timey(ctx.offset)));
+            }
+        });
+
         app.add_observer(spawn_person);
         app.add_observer(kill_person);
         app.add_observer(spawn_bodypart);
@@ -85,24 +155,33 @@ fn spawn_person(
     trigger: Trigger<SpawnPerson>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    anims: Res<PersonAnimations>,
 ) {
     let event = trigger.event();
     let normal = event.normal;
     let speed = event.speed;
     let posp = event.pos;
     let id = trigger.entity();
+    let appearance = Appearance::from_seed(event.seed);
 
     let perp = commands.spawn((
         Name::new("Person"),
         Transform::from_translation(posp)
-            .looking_to(normal, Dir3::Y),
+            .looking_to(normal, Dir3::Y)
+            .with_scale(Vec3::splat(appearance.scale)),
         Visibility::Visible,
         Person,
         BodyRoot,
+        InteractLayers::PERSON,
         Health(100.0),
         Bob(0.0),
         LookingForWork,
-        Speed(speed)
+        Capabilities::default(),
+        Mass(10.0),
+        Speed(speed),
+        (LodLevel::default(), SimBudget::default()),
+        (Locomotion::default(), anims.player(), AnimationGraphHandle(anims.graph.clone())),
+        (appearance.clone(), AppearanceMaterials::default())
     )).with_children(|parent| {
 
         let h = 1.6;
@@ -110,6 +189,8 @@ fn spawn_person(
         parent
             .spawn((
                 Name::new("BodyOdy"),
+                BodyPart(BodyPartType::Torso),
+                LimbHealth(BodyPartType::Torso.max_health()),
                 GltfBodyPart,
                 SceneRoot(
                     asset_server
@@ -121,6 +202,8 @@ fn spawn_person(
                 body_parent
                     .spawn((
                         Name::new("Arm1"),
+                        BodyPart(BodyPartType::Arm),
+                        LimbHealth(BodyPartType::Arm.max_health()),
                         GltfBodyPart,
                         SceneRoot(
                             asset_server
@@ -134,6 +217,8 @@ fn spawn_person(
                 body_parent
                     .spawn((
                         Name::new("Arm2"),
+                        BodyPart(BodyPartType::Arm),
+                        LimbHealth(BodyPartType::Arm.max_health()),
                         GltfBodyPart,
                         SceneRoot(
                             asset_server
@@ -146,16 +231,20 @@ fn spawn_person(
                 body_parent
                     .spawn((
                         Name::new("head"),
+                        BodyPart(BodyPartType::Head),
+                        LimbHealth(BodyPartType::Head.max_health()),
                         GltfBodyPart,
                         SceneRoot(
                             asset_server
-                                .load(GltfAssetLabel::Scene(0).from_asset(if speed < 0.3 { "head.glb" } else { "serhead.glb"}))),
+                                .load(GltfAssetLabel::Scene(0).from_asset(appearance.head.asset()))),
                         Transform::from_xyz(0.0, 0.71, -0.01)
                     ));
 
                 body_parent
                     .spawn((
                         Name::new("leg1"),
+                        BodyPart(BodyPartType::Leg),
+                        LimbHealth(BodyPartType::Leg.max_health()),
                         GltfBodyPart,
                         Timey(0.9),
                         SceneRoot(
@@ -170,6 +259,8 @@ fn spawn_person(
                 body_parent
                     .spawn((
                         Name::new("leg2"),
+                        BodyPart(BodyPartType::Leg),
+                        LimbHealth(BodyPartType::Leg.max_health()),
                         GltfBodyPart,
                         Timey(9.5),
                         SceneRoot(
@@ -197,23 +288,19 @@ fn spawn_bodypart(
     trigger: Trigger<SpawnBodyPart>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    query: Query<&GlobalTransform, With<Parent>>,
 ) {
     let event = trigger.event();
     let id = trigger.entity();
     let pos = event.pos;
     let item_id = event.item_id;
     let normal = event.normal;
-    //let look_t = Transform::IDENTITY.look_to(normal, Dir3::Y);
-    let q = Quat::from_euler(EulerRot::XYZ, normal.x, normal.y, normal.z);
 
-    let rotation = match query.get(id) {
-        Ok(t) => t.rotation() + q,
-        _ => q
+    // Which way out of the surface the part should point
+    let up = match item_id {
+        ItemId::Leg => Dir3::NEG_Y,
+        _ => Dir3::Y
     };
 
-    info!("parrr {:?}", rotation);
-
     let perp = match item_id {
         ItemId::Head => {
             commands
@@ -221,16 +308,16 @@ fn spawn_bodypart(
                     Name::new("serhead"),
                     Visibility::Visible,
                     GltfBodyPart,
+                    BodyPart(BodyPartType::Head),
+                    LimbHealth(BodyPartType::Head.max_health()),
                     BodyRoot,
+                    InteractLayers::BODY_PART,
+                    Carryable,
+                    Mass(4.0),
                     SceneRoot(
                         asset_server
                             .load(GltfAssetLabel::Scene(0).from_asset("serhead.glb"))),
-                    Transform::from_translation(pos)
-                        .with_rotation(rotation)
-                        //.looking_to(normal, Dir3::Y)
-                        .with_scale(Vec3::splat(1.5))
-                        //.with_rotation(Quat::from_euler(EulerRot::XYZ, normal.x, normal.y, normal.z))
-
+                    Transform::from_scale(Vec3::splat(1.5))
                 )).id()
         },
         ItemId::Leg => {
@@ -239,14 +326,16 @@ fn spawn_bodypart(
                     Name::new("leg1"),
                     Timey(0.9),
                     Visibility::Visible,
+                    BodyPart(BodyPartType::Leg),
+                    LimbHealth(BodyPartType::Leg.max_health()),
                     BodyRoot,
+                    InteractLayers::BODY_PART,
+                    Carryable,
+                    Mass(6.0),
                     SceneRoot(
                         asset_server
                             .load(GltfAssetLabel::Scene(0).from_asset("leg.glb"))),
-                    Transform::from_translation(pos)
-                        .looking_to(normal, Dir3::Y)
-                        //.with_rotation(Quat::from_euler(EulerRot::XYZ, normal.x, normal.y, normal.z))
-
+                    Transform::IDENTITY
                 )).id()
         },
         _ => {
@@ -256,39 +345,59 @@ fn spawn_bodypart(
                     Timey(0.9),
                     Visibility::Visible,
                     BodyRoot,
+                    Carryable,
                     SceneRoot(
                         asset_server
                             .load(GltfAssetLabel::Scene(0).from_asset("plinth.glb"))),
-                    Transform::from_translation(pos)
-                        .looking_to(normal, Dir3::Y)
-                        //.with_rotation(Quat::from_euler(EulerRot::XYZ, normal.x, normal.y, normal.z))
-
+                    Transform::IDENTITY
                 )).id()
         }
     };
 
     if let Some(_e) = commands.get_entity(id) {
-        commands.entity(perp).remove::<BodyRoot>();
-        commands.entity(id).add_child(perp);
+        commands.trigger_targets(AttachPart { parent: id, point: pos, normal, up }, perp);
+    } else {
+        // Nothing to stick it to, stand it up in the world
+        let rotation = Quat::from_rotation_arc(*up, normal.normalize_or(Vec3::Y));
+        commands.entity(perp).insert(
+            Transform::from_translation(pos).with_rotation(rotation)
+        );
     }
 
 }
 
 fn move_person(
     time: Res<Time>,
-    mut q: Query<(&mut Transform, &Speed), With<Person>>
+    mut q: Query<(&mut Transform, &Speed, &Capabilities, Option<&TownsfolkTask>, Option<&SimBudget>, Option<&StatusEffects>), (With<Person>, Without<Knockback>)>
 ) {
-    let dt = time.delta_secs();
-    for (mut transform, speed) in q.iter_mut() {
-        transform.rotate_y(speed.0 * 0.5 * dt);
-        let move_amount = transform.forward() * speed.0 * dt;
+    for (mut transform, speed, caps, task, budget, effects) in q.iter_mut() {
+        if !caps.can_walk {
+            continue;
+        }
+        let dt = match budget {
+            Some(b) if !b.ready => continue,
+            Some(b) => b.dt,
+            None => time.delta_secs()
+        };
+        let mut speed = speed.0 * caps.speed * effects.map_or(1.0, |e| e.speed());
+        // Chasing is done by whoever's hunting
+        if let Some(TownsfolkTask { task: TownsfolkTaskType::Attacking }) = task {
+            continue;
+        }
+        // Running away, not wandering in circles
+        if let Some(TownsfolkTask { task: TownsfolkTaskType::Fleaing }) = task {
+            speed *= 4.0;
+        } else {
+            transform.rotate_y(speed * 0.5 * dt);
+        }
+        let move_amount = transform.forward() * speed * dt;
         transform.translation += move_amount;
         transform.rotation = transform.rotation.normalize();
     }
 }
 
 fn animate_joints(
-    mut joints: Query<(&mut Transform, &Timey), With<Jointy>>,
+    mut joints: Query<(&mut Transform, &Timey), (With<Jointy>, Without<AnimationTarget>)>,
 ) {
     for (mut t, timey) in joints.iter_mut() {
         let sec = timey.0;
@@ -301,7 +410,7 @@ fn animate_joints(
 }
 
 fn animate_joint_cycle(
-    mut joints: Query<(&mut Transform, &Timey), With<JointCycle>>,
+    mut joints: Query<(&mut Transform, &Timey), (With<JointCycle>, Without<AnimationTarget>)>,
 ) {
     for (mut t, timey) in joints.iter_mut() {
         let sec = timey.0 * 5.5;
@@ -311,14 +420,42 @@ fn animate_joint_cycle(
     }
 }
 
+/// Works in world space and lands on anything that counts as ground,
+/// building floors included
 fn apply_knockback(
-    mut q: Query<(&mut Knockback, &mut Transform)>,
-    time: Res<Time>
+    mut q: Query<(Entity, &mut Knockback, &mut Transform, &GlobalTransform, Option<&Parent>)>,
+    globals: Query<&GlobalTransform>,
+    mut ray_cast: MeshRayCast,
+    terrain: Query<(), With<Terrain>>,
+    time: Res<Time>,
+    mut commands: Commands,
 ){
-    for (mut knock, mut t) in q.iter_mut() {
-        knock.duration.tick(time.delta());
-        if !knock.duration.finished() {
-            t.translation += knock.dir * time.delta_secs();
+    let dt = time.delta_secs();
+    for (e, mut knock, mut t, gt, parent) in q.iter_mut() {
+        knock.velocity.y -= GRAVITY * dt;
+        let pos = gt.translation();
+        let mut next = pos + knock.velocity * dt;
+
+        // Look down from above where it was, so falling fast doesn't
+        // go through floors
+        let (ground, _) = ground_below(&mut ray_cast, &terrain, next, pos.y.max(next.y) + GROUND_LOOK);
+        knock.grounded = next.y <= ground;
+        if knock.grounded {
+            next.y = ground;
+        }
+        let parent_gt = parent.and_then(|p| globals.get(p.get()).ok());
+        t.translation = to_parent_space(parent_gt, next);
+        if !knock.grounded {
+            continue;
+        }
+
+        knock.velocity.y = knock.velocity.y.max(0.0);
+        let slow = (1.0 - FRICTION * dt).max(0.0);
+        knock.velocity.x *= slow;
+        knock.velocity.z *= slow;
+
+        if knock.velocity.length() < 0.05 {
+            commands.entity(e).remove::<Knockback>();
         }
     }
 }
@@ -326,13 +463,14 @@ fn apply_knockback(
 fn hit_bodypart(
     trigger: Trigger<HitBodyPart>,
     parent_q: Query<&Parent>,
-    mut persons: Query<&mut Health, With<Person>>,
+    mut persons: Query<(&mut Health, Option<&Mass>, Option<&mut Knockback>), With<Person>>,
+    mut limbs: Query<&mut LimbHealth>,
     mut commands: Commands,
 ) {
     let id = trigger.entity();
 
     let root = parent_q.root_ancestor(id);
-    let Ok(mut p) = persons.get_mut(root) else {
+    let Ok((mut p, mass, knockback)) = persons.get_mut(root) else {
         return;
     };
     if p.0 <= 0.0 {
@@ -343,44 +481,75 @@ fn hit_bodypart(
     let event = trigger.event();
     let dir = event.dir;
     let power = event.power;
+    let damage = limb_damage(event.item_id);
 
-    p.0 -= 25.0;
+    p.0 -= damage.body;
     if p.0 <= 0.0 {
-        commands.trigger_targets(KillPerson, root);
-    } else {
-        commands.entity(root).insert(Knockback {
-            dir: Vec3::from(dir) * Vec3::new(1.0, 0.0, 1.0) * power,
-            duration: Timer::from_seconds(0.2, TimerMode::Once)
-        });
+        commands.trigger_targets(KillPerson { dir: Vec3::from(dir), power }, root);
+        return;
+    }
+
+    // The limb is the closest body part scene above the mesh that was hit
+    let limb = std::iter::once(id)
+        .chain(parent_q.iter_ancestors(id))
+        .find(|e| limbs.contains(*e));
+    if let Some(limb) = limb {
+        if let Ok(mut limb_health) = limbs.get_mut(limb) {
+            limb_health.0 -= damage.limb;
+            info!("limb {:?} at {:?}", limb, limb_health.0);
+            if limb_health.0 <= 0.0 {
+                commands.trigger_targets(SeverLimb, limb);
+            }
+        }
+    }
+
+    for effect in item_statuses(event.item_id, power) {
+        commands.trigger_targets(ApplyStatus(effect), root);
+    }
+
+    // Power is an impulse. Push mostly along the ground, with some lift
+    // so a big enough hit sends them flying.
+    let flat = (Vec3::from(dir) * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero();
+    let kick = impulse(power, (flat + Vec3::Y * 0.5).normalize_or_zero(), mass);
+    match knockback {
+        Some(mut k) => k.velocity += kick,
+        None => {
+            commands.entity(root).insert(Knockback { velocity: kick, grounded: true });
+        }
     }
 
 }
 
 fn kill_person(
     trigger: Trigger<KillPerson>,
-    persons: Query<&GlobalTransform, With<Person>>,
+    mut persons: Query<(&GlobalTransform, &mut Health), With<Person>>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
 ) {
     let id = trigger.entity();
+    let event = trigger.event();
 
-    let pos = match persons.get(id) {
-        Ok(t) => t.translation(),
-        _ => Vec3::ZERO
+    let Ok((t, mut health)) = persons.get_mut(id) else {
+        return;
     };
+    info!("You ded {:?}", t.translation());
+    health.0 = 0.0;
 
-    info!("You ded {:?}", pos);
-
-    commands.entity(id).despawn_recursive();
+    // Stop being a person and go limp, keeping whatever limbs they had
     commands
-        .spawn((
-            SceneRoot(
-                asset_server
-                    .load(GltfAssetLabel::Scene(0).from_asset("dead.glb"))),
-            Transform::from_xyz(pos.x, pos.y , pos.z),
-            Carryable,
-            BodyRoot,
-            Person,
-            Health(0.0)
-        ));
+        .entity(id)
+        .remove::<(
+            Speed,
+            Bob,
+            LookingForWork,
+            TownsfolkTask,
+            Hostile,
+            Knockback,
+            StatusEffects,
+            Capabilities,
+            Locomotion,
+            AnimationPlayer,
+            AnimationGraphHandle,
+        )>()
+        .insert((Carryable, Corpse::default()));
+    commands.trigger_targets(Ragdoll { dir: event.dir, power: event.power }, id);
 }
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+
+pub const GRAVITY: f32 = 9.8;
+/// How fast things slide to a stop on the ground, per second
+pub const FRICTION: f32 = 4.0;
+
+/// Heavier things get pushed around less, and are harder to carry
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Mass(pub f32);
+
+impl Default for Mass {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// A world space point in the local space of a child of `parent`, or
+/// as it is for something with no parent
+pub fn to_parent_space(parent: Option<&GlobalTransform>, point: Vec3) -> Vec3 {
+    parent.map_or(point, |p| p.affine().inverse().transform_point3(point))
+}
+
+/// Velocity change from an impulse
+pub fn impulse(power: f32, dir: Vec3, mass: Option<&Mass>) -> Vec3 {
+    dir * power / mass.map_or(1.0, |m| m.0.max(0.01))
+}
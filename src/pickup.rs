@@ -0,0 +1,248 @@
+use std::f32::consts::*;
+
+use bevy::prelude::*;
+
+use crate::actions::{Action, ActionState};
+use crate::camera::CameraMode;
+use crate::carry::{carry_input, Carrying, PICKUP_REACH};
+use crate::hotbar::HotbarSelected;
+use crate::interact::{InteractLayers, Interactables};
+use crate::inventory::{Inventory, ItemId, ItemStack};
+use crate::person::{Knockback, Pickable};
+use crate::player::{Player, RaycastTarget};
+use crate::vitals::Dead;
+
+pub struct PickupPlugin;
+
+/// How close the player has to walk to an item to pick it up
+const WALK_OVER_RADIUS: f32 = 1.2;
+/// Seconds before something dropped can be walked over and picked up,
+/// so it isn't picked straight back up
+const PICKUP_DELAY: f32 = 1.5;
+/// How hard things get tossed out in front when dropped
+const DROP_THROW: f32 = 3.0;
+
+/// Seconds left until walking over this item picks it up
+#[derive(Component)]
+pub struct PickupDelay(pub f32);
+
+/// Put an item in the world, flying off at `velocity` until it lands
+#[derive(Debug, Event)]
+pub struct SpawnItem {
+    pub stack: ItemStack,
+    pub pos: Vec3,
+    pub velocity: Vec3,
+}
+
+/// Take `num` out of one of the player's inventory slots and throw them
+/// down in front of them
+#[derive(Debug, Event)]
+pub struct DropItems {
+    pub slot: u32,
+    pub num: u32,
+}
+
+#[derive(Resource)]
+struct ItemAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, (
+            // Drop only lets go of the selected item when nothing's carried
+            drop_selected.before(carry_input),
+            count_down_delay,
+            walk_over_items,
+            interact_with_items
+        ));
+        app.add_observer(spawn_item);
+        app.add_observer(drop_items);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ItemAssets {
+        mesh: meshes.add(Cuboid::new(0.3, 0.3, 0.3)),
+        material: materials.add(StandardMaterial {
+            base_color: Srgba::hex("#aa9955").unwrap().into(),
+            ..default()
+        }),
+    });
+}
+
+/// The scene an item shows as when dropped, if it has one, and where
+/// that sits relative to where the item rests
+fn item_model(item_id: ItemId) -> (Option<&'static str>, Transform) {
+    let lying = Transform::from_xyz(0.0, 0.1, 0.0)
+        .with_rotation(Quat::from_rotation_x(FRAC_PI_2));
+    match item_id {
+        ItemId::Head => (Some("serhead.glb"), Transform::IDENTITY),
+        ItemId::Torso => (Some("body.glb"), lying),
+        ItemId::Leg => (Some("leg.glb"), lying),
+        ItemId::Arm => (Some("arm.glb"), lying),
+        ItemId::Sword => (Some("cleaver.glb"), lying),
+        ItemId::Gun => (Some("gun.glb"), lying),
+        _ => (None, Transform::from_xyz(0.0, 0.15, 0.0)),
+    }
+}
+
+fn spawn_item(
+    trigger: Trigger<SpawnItem>,
+    assets: Res<ItemAssets>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let event = trigger.event();
+    let stack = event.stack;
+    let (scene, offset) = item_model(stack.item_id);
+    info!("{} {:?} on the ground", stack.num, stack.item_id);
+
+    let mut item = commands.spawn((
+        Name::new(format!("{:?}", stack.item_id)),
+        stack,
+        PickupDelay(PICKUP_DELAY),
+        Knockback { velocity: event.velocity, grounded: false },
+        Transform::from_translation(event.pos),
+        // Knockback moves it from where it is in the world, which has to
+        // be right before the first propagation
+        GlobalTransform::from_translation(event.pos),
+        Visibility::Visible,
+    ));
+    match scene {
+        Some(scene) => {
+            item.with_child((
+                SceneRoot(asset_server.load(GltfAssetLabel::Scene(0).from_asset(scene))),
+                offset,
+            ));
+        }
+        None => {
+            item.with_child((
+                Pickable,
+                Mesh3d(assets.mesh.clone()),
+                MeshMaterial3d(assets.material.clone()),
+                offset,
+            ));
+        }
+    }
+}
+
+fn drop_items(
+    trigger: Trigger<DropItems>,
+    mut player: Query<(&Transform, &mut Inventory), (With<Player>, Without<Dead>)>,
+    mut commands: Commands,
+) {
+    let Ok((t, mut inv)) = player.get_single_mut() else {
+        return;
+    };
+    let event = trigger.event();
+    // Hands stay on
+    if inv.map.get(&event.slot).is_none_or(|s| s.item_id == ItemId::Fist) {
+        return;
+    }
+    let Some(stack) = inv.take_from_slot(event.slot, event.num) else {
+        return;
+    };
+    let forward = *t.forward();
+    commands.trigger(SpawnItem {
+        stack,
+        pos: t.translation + Vec3::Y * 1.2 + forward * 0.6,
+        velocity: (forward + Vec3::Y * 0.5) * DROP_THROW,
+    });
+}
+
+/// Drop one of whatever's selected, when there's nothing in the
+/// player's arms to drop instead
+fn drop_selected(
+    actions: Res<ActionState>,
+    camera: Res<CameraMode>,
+    hotbar: Query<&HotbarSelected>,
+    player: Query<&Carrying, (With<Player>, Without<Dead>)>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::Drop) || !camera.controls_player() {
+        return;
+    }
+    let (Ok(carrying), Ok(selected)) = (player.get_single(), hotbar.get_single()) else {
+        return;
+    };
+    if carrying.entity.is_none() {
+        commands.trigger(DropItems { slot: selected.0, num: 1 });
+    }
+}
+
+fn count_down_delay(
+    time: Res<Time>,
+    mut items: Query<(Entity, &mut PickupDelay)>,
+    mut commands: Commands,
+) {
+    for (e, mut delay) in items.iter_mut() {
+        delay.0 -= time.delta_secs();
+        if delay.0 <= 0.0 {
+            commands.entity(e).remove::<PickupDelay>();
+        }
+    }
+}
+
+/// Put as much of `stack` as fits in `inv`, and take the item out of
+/// the world if that was all of it
+fn pick_up(e: Entity, stack: &mut ItemStack, inv: &mut Inventory, commands: &mut Commands) {
+    let left = inv.add_item(*stack);
+    if left == stack.num {
+        return;
+    }
+    info!("picked up {} {:?}", stack.num - left, stack.item_id);
+    stack.num = left;
+    if left == 0 {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+fn walk_over_items(
+    mut player: Query<(&Transform, &mut Inventory), (With<Player>, Without<Dead>)>,
+    mut items: Query<(Entity, &GlobalTransform, &mut ItemStack), Without<PickupDelay>>,
+    mut commands: Commands,
+) {
+    let Ok((t, mut inv)) = player.get_single_mut() else {
+        return;
+    };
+    for (e, it, mut stack) in items.iter_mut() {
+        if it.translation().distance(t.translation) <= WALK_OVER_RADIUS && inv.space_for(stack.item_id) > 0 {
+            pick_up(e, &mut stack, &mut inv, &mut commands);
+        }
+    }
+}
+
+/// Interact picks up an item from further away, delay or not
+#[allow(clippy::too_many_arguments)]
+fn interact_with_items(
+    actions: Res<ActionState>,
+    camera: Res<CameraMode>,
+    ray_target: Res<RaycastTarget>,
+    mut interactables: Interactables,
+    mut player: Query<&mut Inventory, (With<Player>, Without<Dead>)>,
+    mut items: Query<&mut ItemStack>,
+    parent_q: Query<&Parent>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::Interact) || !camera.controls_player() {
+        return;
+    }
+    let Ok(mut inv) = player.get_single_mut() else {
+        return;
+    };
+    let ray = Ray3d::new(ray_target.origin, ray_target.dir);
+    let Some(hit) = interactables.nearest(ray, PICKUP_REACH, InteractLayers::PROP) else {
+        return;
+    };
+    let root = parent_q.root_ancestor(hit.mesh);
+    if let Ok(mut stack) = items.get_mut(root) {
+        pick_up(root, &mut stack, &mut inv, &mut commands);
+    }
+}
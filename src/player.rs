@@ -1,41 +1,37 @@
-use bevy::math::VectorSpace;
 use bevy::prelude::*;
-use bevy::input::mouse::AccumulatedMouseMotion;
-use bevy::pbr::{NotShadowCaster, NotShadowReceiver};
 use std::f32::consts::*;
 
 use crate::inventory::{Inventory,ItemStack,ItemId};
-use crate::person::{HitBodyPart, Person, Pickable, SpawnBodyPart, SpawnPerson, BodyRoot};
-use crate::hotbar::{HotbarSelected, HotbarChangeSelected};
-use crate::terrain::Terrain;
+use crate::vitals::{Dead, PLAYER_HEALTH};
+use crate::person::{Health, SpawnBodyPart, SpawnPerson};
+use crate::carry::{Carrying, PICKUP_REACH};
+use crate::limb::Capabilities;
+use crate::physics::Mass;
+use crate::status::StatusEffects;
+use crate::controller::CharacterController;
+use crate::gun::Magazine;
+use crate::tool::{Tool, ToolAppExt, ToolRegistry, ToolState, ToolTarget, ToolUse, UseUpItem, Viewmodel};
+use crate::interact::{InteractLayers, Interactables};
+use crate::camera::{CameraMode, MainCamera};
+use crate::actions::{Action, ActionState};
 
 pub struct PlayerPlugin;
 
 #[derive(Component)]
 pub struct Player;
 
-#[derive(Component)]
-pub struct MainCamera;
-
-#[derive(Component)]
-pub struct ToolViz;
-
-#[derive(Debug, Event)]
-pub struct CarryStuff {
-    pub entity: Entity,
-    pub point: Vec3
-}
-
-#[derive(Component)]
-struct Carrying(Option<Entity>);
-
 #[derive(Resource)]
-struct RaycastTarget {
-    dir: Dir3,
-    point: Option<Vec3>,
-    normal: Vec3,
-    mesh: Option<Entity>,
-    mesh_point: Vec3
+pub struct RaycastTarget {
+    /// Where the aim starts, level with the player's eye
+    pub origin: Vec3,
+    pub dir: Dir3,
+    pub point: Option<Vec3>,
+    pub normal: Vec3,
+    pub mesh: Option<Entity>,
+    /// From the eye to `point`
+    pub distance: f32,
+    pub mesh_point: Vec3,
+    pub mesh_normal: Vec3
 }
 
 impl Plugin for PlayerPlugin {
@@ -43,33 +39,34 @@ impl Plugin for PlayerPlugin {
         app.add_systems(Startup, setup);
         app.add_systems(Update, (
             ray_cast_forward,
-            ray_cast_down,
             move_player_pos,
             move_player_view,
-            use_tool,
             cursor_ray_align
         ));
-        app.add_observer(switch_tool_viz);
-        app.add_observer(carry_stuff);
+        app.register_tool(ItemId::Cloner, ClonerTool);
+        app.register_tool(ItemId::Head, PlaceTool(ItemId::Head));
+        app.register_tool(ItemId::Leg, PlaceTool(ItemId::Leg));
     }
 }
 
 #[derive(Component)]
-struct Cursor;
+pub(crate) struct Cursor;
 
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
 ) {
 
     commands.insert_resource(RaycastTarget {
+        origin: Vec3::ZERO,
         dir: Dir3::Z,
         point: None,
         normal: Vec3::ZERO,
         mesh: None,
-        mesh_point: Vec3::ZERO
+        distance: 0.0,
+        mesh_point: Vec3::ZERO,
+        mesh_normal: Vec3::Y
     });
 
     let mat = MeshMaterial3d(materials.add(StandardMaterial {
@@ -86,108 +83,43 @@ fn setup(
     ));
 
     let mut inv = Inventory::new();
-    inv.add_item(ItemStack {
-        item_id: ItemId::Fist,
-        item_type: ItemId::Fist.get_default_type(),
-        num: 1
-    });
-    inv.add_item(ItemStack {
-        item_id: ItemId::Sword,
-        item_type: ItemId::Sword.get_default_type(),
-        num: 1
-    });
-    inv.add_item(ItemStack {
-        item_id: ItemId::Cloner,
-        item_type: ItemId::Cloner.get_default_type(),
-        num: 1
-    });
-    inv.add_item(ItemStack {
-        item_id: ItemId::Head,
-        item_type: ItemId::Head.get_default_type(),
-        num: 1
-    });
-    inv.add_item(ItemStack {
-        item_id: ItemId::Leg,
-        item_type: ItemId::Leg.get_default_type(),
-        num: 1
-    });
+    inv.add_item(ItemStack::new(ItemId::Fist, 1));
+    inv.add_item(ItemStack::new(ItemId::Sword, 1));
+    inv.add_item(ItemStack::new(ItemId::Gun, 1));
+    inv.add_item(ItemStack::new(ItemId::Cloner, 1));
+    inv.add_item(ItemStack::new(ItemId::Head, 4));
+    inv.add_item(ItemStack::new(ItemId::Leg, 4));
+    inv.add_item(ItemStack::new(ItemId::Ammo, 24));
 
     commands.spawn((
         Name::new("Player"),
         Player,
-        Carrying(None),
+        Health(PLAYER_HEALTH),
+        StatusEffects::default(),
+        CharacterController::default(),
+        Carrying::default(),
+        // Two arms' worth of carrying
+        Capabilities::default(),
+        ToolState::default(),
+        Magazine::full(),
         Transform::from_xyz(0., 0., 25.0),
         Visibility::Visible,
         inv
-    )).with_children(|parent| {
-        parent.spawn((
-            Camera3d::default(),
-            Camera {
-                hdr: true,
-                ..default()
-            },
-            Transform::from_xyz(0., 1.5, 0.),
-                //.looking_to(-Dir3::Z, Vec3::Y),
-            MainCamera
-        ));
-
-        parent
-            .spawn((
-                Name::new("Hand"),
-                ToolViz,
-                SceneRoot(
-                    asset_server
-                        .load(GltfAssetLabel::Scene(0).from_asset("hand.glb"))),
-                Transform::from_xyz(0.7, 0.8, -1.55)
-                    .with_rotation(Quat::from_rotation_y(PI / 1.))
-                    .with_scale(Vec3::splat(3.0)),
-                NotShadowCaster
-
-            ));
-
-        parent
-            .spawn((
-                Name::new("Cleaver"),
-                ToolViz,
-                SceneRoot(
-                    asset_server
-                        .load(GltfAssetLabel::Scene(0).from_asset("cleaver.glb"))),
-                Transform::from_xyz(0.65, 0.7, -1.75)
-                    .with_rotation(Quat::from_euler(EulerRot::YXZ, -PI/2.5, 0., -PI / 2.))
-                    .with_scale(Vec3::splat(2.0)),
-                NotShadowCaster,
-                Visibility::Hidden
-            ));
-
-        parent
-            .spawn((
-                Name::new("Gun"),
-                ToolViz,
-                SceneRoot(
-                    asset_server
-                        .load(GltfAssetLabel::Scene(0).from_asset("gun.glb"))),
-                Transform::from_xyz(0.65, 0.6, -1.75)
-                    .with_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, -PI/2.5, 0.))
-                    .with_scale(Vec3::splat(2.0)),
-                NotShadowCaster,
-                NotShadowReceiver,
-                Visibility::Hidden
-            ));
-
-
-    });
-
-
+    ));
 }
 
 fn move_player_view(
-    accumulated_mouse_motion: Res<AccumulatedMouseMotion>,
-    mut player: Query<&mut Transform, With<Player>>,
+    actions: Res<ActionState>,
+    camera: Res<CameraMode>,
+    mut player: Query<&mut Transform, (With<Player>, Without<Dead>)>,
 ) {
     let Ok(mut transform) = player.get_single_mut() else {
         return;
     };
-    let delta = accumulated_mouse_motion.delta;
+    if !camera.controls_player() {
+        return;
+    }
+    let delta = actions.look;
 
     if delta != Vec2::ZERO {
         let delta_yaw = -delta.x * 0.002;
@@ -204,122 +136,81 @@ fn move_player_view(
 }
 
 fn move_player_pos(
-    time: Res<Time>,
-    input: Res<ButtonInput<KeyCode>>,
-    mut player: Query<&mut Transform, With<Player>>,
+    actions: Res<ActionState>,
+    camera: Res<CameraMode>,
+    mut player: Query<(&Transform, &StatusEffects, &Carrying, &Capabilities, &mut CharacterController), (With<Player>, Without<Dead>)>,
+    masses: Query<&Mass>,
 ) {
-    let mut transform = player.single_mut();
-
-    let mut sp = 1.0;
-    if input.pressed(KeyCode::ShiftLeft) {
-        sp *= 5.0;
+    let Ok((transform, effects, carrying, caps, mut controller)) = player.get_single_mut() else {
+        return;
+    };
+    if !camera.controls_player() {
+        controller.wish = Vec3::ZERO;
+        return;
     }
 
-    let mut mo = Vec3::new(0.0, 0.0, 0.0);
-    if input.pressed(KeyCode::KeyW) {
-        mo += transform.local_z() * -sp;
-    }
-    if input.pressed(KeyCode::KeyS) {
-        mo += transform.local_z() * sp;
-    }
-    if input.pressed(KeyCode::KeyA) {
-        mo += transform.local_x() * -sp;
+    let mut sp = effects.speed();
+    if let Some(held) = carrying.entity {
+        sp *= Carrying::speed(masses.get(held).map_or(Mass::default().0, |m| m.0), caps.carry_capacity);
     }
-    if input.pressed(KeyCode::KeyD) {
-        mo += transform.local_x() * sp;
+    if actions.pressed(Action::Sprint) {
+        sp *= 5.0;
     }
 
-    transform.translation += mo * time.delta_secs() * 8.0;
-    //transform.translation.y = 0.0; // Force to ground
+    let mo = transform.local_z() * -actions.movement.y * sp
+        + transform.local_x() * actions.movement.x * sp;
+
+    // Looking up or down shouldn't walk us into the sky
+    let flat = Vec3::new(mo.x, 0.0, mo.z).normalize_or_zero() * mo.length();
+    controller.wish = flat * 8.0;
+
+    if actions.just_pressed(Action::Jump) {
+        controller.jump();
+    }
 }
 
+/// Aim at the nearest thing the tool in hand can reach. Out of first
+/// person that's along the camera, so it's what the crosshair is on.
 fn ray_cast_forward(
-    mut ray_cast: MeshRayCast,
-    player_query: Query<(&Transform, &GlobalTransform), With<Player>>,
-    meshes_query: Query<&GlobalTransform, With<Pickable>>,
+    mut interactables: Interactables,
+    registry: Res<ToolRegistry>,
+    mode: Res<CameraMode>,
+    player_query: Query<(&Transform, &GlobalTransform, &ToolState), With<Player>>,
+    cameras: Query<&GlobalTransform, With<MainCamera>>,
     mut ray_target: ResMut<RaycastTarget>,
 ) {
-    let (transform, global_transform) = player_query.single();
+    let (transform, global_transform, state) = player_query.single();
     let pos = transform.translation;
-    let ray = Ray3d::new(Vec3::new(pos.x, pos.y + 1.5, pos.z),  global_transform.forward());
-
-    let filter = |entity| meshes_query.contains(entity);
-    // let early_exit_test = |_entity| false;
-
-    let settings = RayCastSettings::default()
-        .with_filter(&filter);
-        //.with_early_exit_test(&early_exit_test)
+    let eye = Vec3::new(pos.x, pos.y + 1.5, pos.z);
+    let ray = match cameras.get_single() {
+        Ok(camera) if *mode != CameraMode::FirstPerson => {
+            // Start level with the eye, so reach counts from the player
+            // and nothing between them and the camera gets in the way
+            let dir = camera.forward();
+            let from = camera.translation();
+            Ray3d::new(from + dir * (eye - from).dot(*dir).max(0.0), dir)
+        }
+        _ => Ray3d::new(eye, global_transform.forward()),
+    };
+    let tool = state.item_id.and_then(|id| registry.get(id));
+    // Far enough to pick things up, whatever the tool reaches
+    let reach = tool.map_or(6.0, |t| t.reach()).max(PICKUP_REACH);
+    let layers = tool.map_or(InteractLayers::ALL, |t| t.layers());
 
+    ray_target.origin = ray.origin;
     ray_target.dir = ray.direction;
-    let hits = ray_cast.cast_ray(ray, &settings);
-
-    if hits.len() == 0 {
+    let Some(hit) = interactables.nearest(ray, reach, layers) else {
         ray_target.point = None;
         ray_target.mesh = None;
         return;
-    }
-
-    for (e, rmh) in hits.iter() {
-        let world_pos = rmh.point;
-        let normal = rmh.normal;
-        // Hit position to local space
-        let mesh_local_pos = meshes_query.get(*e).unwrap().affine().inverse().transform_point3(world_pos);
-
-        ray_target.point = Some(world_pos);
-        ray_target.normal = normal;
-        ray_target.mesh = Some(*e);
-        ray_target.mesh_point = mesh_local_pos;
-    }
-}
-
-fn ray_cast_down(
-    mut ray_cast: MeshRayCast,
-    mut player: Query<(&mut Transform, &GlobalTransform), With<Player>>,
-    query: Query<(), With<Terrain>>,
-) {
-    let (mut transform, global_transform) = player.single_mut();
-    let pos = transform.translation;
-    let ray = Ray3d::new(
-        Vec3::new(pos.x, pos.y+0.8, pos.z),
-        global_transform.down()
-    );
-
-    let filter = |entity| query.contains(entity);
-    let settings = RayCastSettings::default()
-        .with_filter(&filter);
-
-    let hits = ray_cast.cast_ray(ray, &settings);
-    if hits.len() == 0 {
-        transform.translation.y += 0.1;
-        return;
-    }
-    for (_e, rmh) in hits.iter() {
-        if rmh.distance < 1.5 {
-            transform.translation.y = rmh.point.y+0.1;
-        } else {
-            transform.translation.y -= 0.1;
-        }
-    }
-}
-
+    };
 
-fn switch_tool_viz(
-    trigger: Trigger<HotbarChangeSelected>,
-    mut tools: Query<(&mut Visibility, &Name), With<ToolViz>>
-) {
-    let next = trigger.event().slot_id;
-    for (mut vis, name) in tools.iter_mut() {
-        *vis = Visibility::Hidden;
-        if name.starts_with("Hand") && next != 1 && next != 2 {
-            *vis = Visibility::Visible;
-        }
-        if name.starts_with("Cleaver") && next == 1 {
-            *vis = Visibility::Visible;
-        }
-        if name.starts_with("Gun") && next == 2 {
-            *vis = Visibility::Visible;
-        }
-    }
+    ray_target.point = Some(hit.point);
+    ray_target.normal = hit.normal;
+    ray_target.mesh = Some(hit.mesh);
+    ray_target.distance = hit.distance;
+    ray_target.mesh_point = hit.mesh_point;
+    ray_target.mesh_normal = hit.mesh_normal;
 }
 
 fn cursor_ray_align(
@@ -337,104 +228,80 @@ fn cursor_ray_align(
     }
 }
 
+/// Empty handed, or holding something that isn't a tool
+pub fn hand() -> Viewmodel {
+    Viewmodel {
+        scene: "hand.glb",
+        transform: Transform::from_xyz(0.7, 0.8, -1.55)
+            .with_rotation(Quat::from_rotation_y(PI / 1.))
+            .with_scale(Vec3::splat(3.0)),
+    }
+}
 
-fn use_tool(
-    ray_target: ResMut<RaycastTarget>,
-    hotbar: Query<&HotbarSelected>,
-    buttons: Res<ButtonInput<MouseButton>>,
-    roots: Query<(Entity, &mut Transform), With<BodyRoot>>,
-    inv: Query<&Inventory, With<Player>>,
-    parent_q: Query<&Parent>,
+struct ClonerTool;
 
-    mut commands: Commands
+impl Tool for ClonerTool {
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        let Some(target) = ctx.target else {
+            return;
+        };
+        commands.trigger_targets(
+            SpawnPerson { pos: target.mesh_point, speed: 0.0, normal: target.normal, seed: rand::random() },
+            target.mesh
+        );
+    }
 
-) {
-    let Some(_point) = ray_target.point else {
-        return;
-    };
-    let Some(mesh) = ray_target.mesh else {
-        return;
-    };
+    fn reach(&self) -> f32 {
+        50.0
+    }
 
-    if !buttons.just_pressed(MouseButton::Left) {
-        return;
+    fn verb(&self, _target: &ToolTarget) -> Option<String> {
+        Some("grow a person".to_string())
     }
 
-    let selected = hotbar.single().0;
-    let inv_player = inv.single();
-    let tool = inv_player.map.get(&selected);
-    let tool_id = tool.map(|t| t.item_id).unwrap_or(ItemId::Fist);
+    /// People grow out of the ground, not out of trees
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::TERRAIN | InteractLayers::BUILDING
+    }
 
-    let normal = ray_target.normal;
-    let mesh_point = ray_target.mesh_point;
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        Some(Viewmodel {
+            scene: "gun.glb",
+            transform: Transform::from_xyz(0.65, 0.6, -1.75)
+                .with_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, -PI/2.5, 0.))
+                .with_scale(Vec3::splat(2.0)),
+        })
+    }
+}
 
-    let root_ancestor = parent_q.root_ancestor(mesh);
+/// Sticks a body part onto whatever it's pointed at
+struct PlaceTool(ItemId);
 
-    if tool_id == ItemId::Cloner {
+impl Tool for PlaceTool {
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        let Some(target) = ctx.target else {
+            return;
+        };
         commands.trigger_targets(
-            SpawnPerson { pos: mesh_point, speed: 0.0, normal },
-            mesh
+            SpawnBodyPart { pos: target.mesh_point, item_id: self.0, normal: target.mesh_normal },
+            target.mesh
         );
-        return;
+        commands.trigger_targets(UseUpItem { item_id: self.0, num: 1 }, ctx.user);
     }
 
-    if tool_id == ItemId::Sword {
-        commands.entity(mesh).remove_parent();
-        commands.entity(mesh).despawn_recursive();
-    } else if tool_id == ItemId::Fist {
-        commands.trigger_targets(
-            HitBodyPart { item_id: ItemId::Apple, dir: ray_target.dir, power: 20.0 },
-            mesh
-        );
-    } else if tool_id == ItemId::Head {
-        // Spawn the thing.
-        commands.trigger_targets(
-            SpawnBodyPart { pos: mesh_point, item_id: ItemId::Head, normal },
-            mesh
-        );
-    } else if tool_id == ItemId::Leg {
-        if let Ok((body_root, transform)) = roots.get(root_ancestor) { //)) let Ok((person, mut transform)) = persons.get_mut(mesh) {
-            info!("got a body_root...");
-            //commands.entity(body_root).remove::<Person>();
-            //transform.translation = Vec3::ZERO;
-            commands.trigger( CarryStuff { entity: body_root, point: transform.translation });
-        } else {
-            info!("no person");
-            commands.trigger_targets(
-                SpawnBodyPart { pos: mesh_point, item_id: ItemId::Leg, normal },
-                mesh
-            );
-        }
+    fn reach(&self) -> f32 {
+        20.0
     }
 
-}
-
-
-fn carry_stuff(
-    trigger: Trigger<CarryStuff>,
-    mut commands: Commands<'_, '_>,
-    mut player_query: Query<(Entity, &mut Carrying), With<Player>>,
-    mut roots: Query<&mut Transform, With<BodyRoot>>,
-) {
-    let (player, mut carrying)  = player_query.single_mut();
-
-    let event = trigger.event();
-    let thing_to_carry = event.entity;
-    let pos = event.point;
-
-    if carrying.0.is_some() {
-        carrying.0.translation = pos;
-        return;
+    fn verb(&self, _target: &ToolTarget) -> Option<String> {
+        Some(format!("attach {:?}", self.0).to_lowercase())
     }
 
-    let Ok(mut thing) = roots.get_mut(thing_to_carry) else {
-        info!("no thing");
-        return;
-    };
-
-    carrying.0 = Some(thing_to_carry);
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::PERSON | InteractLayers::BODY_PART | InteractLayers::PROP
+    }
 
-    info!("{:?}, carry", thing);
-    thing.translation = Vec3::new(0.0, 1.5, -3.0);
-    commands.entity(player).add_child(thing_to_carry);
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        Some(hand())
+    }
 }
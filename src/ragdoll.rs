@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use crate::inventory::BodyPartType;
+use crate::limb::BodyPart;
+use crate::person::Carried;
+use crate::controller::ground_below;
+use crate::physics::{to_parent_space, FRICTION, GRAVITY};
+use crate::terrain::Terrain;
+
+pub struct RagdollPlugin;
+
+/// How far the root sits above the ground once the body is lying down
+const LYING_HEIGHT: f32 = 0.15;
+/// How far above a falling body the ground is looked for from
+const GROUND_LOOK: f32 = 0.5;
+/// Angular acceleration of a body toppling over its feet
+const TOPPLE: f32 = 6.0;
+const JOINT_DAMPING: f32 = 3.0;
+/// Furthest a limb can swing from where it was attached
+const JOINT_LIMIT: f32 = 1.2;
+
+/// The root of a falling body. The feet are the pivot, so tipping
+/// the root over lays the whole hierarchy down.
+#[derive(Component)]
+pub struct RagdollBody {
+    pub velocity: Vec3,
+    pub angular: Vec3,
+    grounded: bool,
+}
+
+/// A limb swinging loose from its parent part
+#[derive(Component)]
+pub struct RagdollJoint {
+    pub angular: Vec3,
+    rest: Quat,
+}
+
+#[derive(Component)]
+pub struct Settled;
+
+#[derive(Debug, Event)]
+pub struct Ragdoll {
+    pub dir: Vec3,
+    pub power: f32
+}
+
+impl Plugin for RagdollPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            simulate_bodies,
+            simulate_joints,
+            settle
+        ).chain());
+        app.add_observer(ragdoll);
+    }
+}
+
+fn ragdoll(
+    trigger: Trigger<Ragdoll>,
+    children: Query<&Children>,
+    parts: Query<(&BodyPart, &Transform)>,
+    mut commands: Commands,
+) {
+    let root = trigger.entity();
+    let Ragdoll { dir, power } = *trigger.event();
+
+    // Fall the way we were hit, or any old way if we weren't
+    let flat = (dir * Vec3::new(1.0, 0.0, 1.0)).normalize_or(Vec3::X);
+    let topple = Vec3::Y.cross(flat);
+
+    commands.entity(root).insert(RagdollBody {
+        velocity: flat * power * 0.2 + Vec3::Y * power * 0.05,
+        angular: topple * (0.5 + power * 0.05),
+        grounded: false
+    });
+
+    let mut kick = 1.0;
+    for e in children.iter_descendants(root) {
+        let Ok((part, t)) = parts.get(e) else {
+            continue;
+        };
+        if part.0 == BodyPartType::Torso {
+            continue;
+        }
+        // Alternate which way limbs are flung so they don't all go together
+        kick = -kick;
+        commands.entity(e).insert(RagdollJoint {
+            angular: (topple + flat * kick) * power * 0.1,
+            rest: t.rotation
+        });
+    }
+}
+
+/// Not jointed rigid bodies: the body topples as one piece about its
+/// feet, and `simulate_joints` swings the limbs off it. Moves in world
+/// space and lands on anything that counts as ground, like knockback.
+fn simulate_bodies(
+    time: Res<Time>,
+    mut ray_cast: MeshRayCast,
+    terrain: Query<(), With<Terrain>>,
+    globals: Query<&GlobalTransform>,
+    mut q: Query<(&mut Transform, &GlobalTransform, Option<&Parent>, &mut RagdollBody), (Without<Settled>, Without<Carried>)>,
+) {
+    let dt = time.delta_secs();
+    for (mut t, gt, parent, mut body) in q.iter_mut() {
+        body.velocity.y -= GRAVITY * dt;
+        let pos = gt.translation();
+        let mut next = pos + body.velocity * dt;
+
+        let up = t.up();
+        let lying = up.y < 0.15;
+
+        // Keep toppling until we're on the floor
+        if !lying {
+            let fall = Vec3::Y.cross(*up).normalize_or_zero();
+            body.angular += fall * TOPPLE * dt;
+        } else {
+            body.angular = Vec3::ZERO;
+        }
+        t.rotate(Quat::from_scaled_axis(body.angular * dt));
+        t.rotation = t.rotation.normalize();
+
+        let (ground, _) = ground_below(&mut ray_cast, &terrain, next, pos.y.max(next.y) + GROUND_LOOK);
+        let rest = if lying { LYING_HEIGHT } else { 0.0 };
+        body.grounded = next.y <= ground + rest;
+        if body.grounded {
+            next.y = ground + rest;
+        }
+        let parent_gt = parent.and_then(|p| globals.get(p.get()).ok());
+        t.translation = to_parent_space(parent_gt, next);
+        if body.grounded {
+            body.velocity.y = 0.0;
+            let slow = (1.0 - FRICTION * dt).max(0.0);
+            body.velocity.x *= slow;
+            body.velocity.z *= slow;
+        }
+    }
+}
+
+fn simulate_joints(
+    time: Res<Time>,
+    mut q: Query<(&mut Transform, &GlobalTransform, &mut RagdollJoint), Without<Settled>>,
+) {
+    let dt = time.delta_secs();
+    for (mut t, gt, mut joint) in q.iter_mut() {
+        // Limbs hang along their -Y, so gravity swings that toward the ground
+        let hang = gt.affine().transform_vector3(Vec3::NEG_Y).normalize_or_zero();
+        let torque = hang.cross(Vec3::NEG_Y);
+        let local_torque = gt.compute_transform().rotation.inverse() * torque;
+
+        joint.angular += local_torque * GRAVITY * dt;
+        joint.angular *= (1.0 - JOINT_DAMPING * dt).max(0.0);
+
+        let next = t.rotation * Quat::from_scaled_axis(joint.angular * dt);
+        if next.angle_between(joint.rest) < JOINT_LIMIT {
+            t.rotation = next.normalize();
+        } else {
+            joint.angular = Vec3::ZERO;
+        }
+    }
+}
+
+fn settle(
+    bodies: Query<(Entity, &RagdollBody, &Transform), Without<Settled>>,
+    joints: Query<&RagdollJoint>,
+    children: Query<&Children>,
+    mut commands: Commands,
+) {
+    for (e, body, t) in bodies.iter() {
+        if !body.grounded || t.up().y >= 0.15 || body.velocity.length() > 0.05 {
+            continue;
+        }
+        let moving = children
+            .iter_descendants(e)
+            .filter_map(|c| joints.get(c).ok())
+            .any(|j| j.angular.length() > 0.05);
+        if moving {
+            continue;
+        }
+        commands.entity(e).insert(Settled);
+        for c in children.iter_descendants(e) {
+            if joints.contains(c) {
+                commands.entity(c).insert(Settled);
+            }
+        }
+    }
+}
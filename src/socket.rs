@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::person::BodyRoot;
+
+pub struct SocketPlugin;
+
+/// Where a part is attached: a point and surface normal in the
+/// parent mesh's local space, and which of the part's own axes
+/// should point out along that normal.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Socket {
+    pub parent: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub up: Dir3,
+}
+
+impl Socket {
+    /// Local transform of the part relative to its parent
+    pub fn transform(&self) -> Transform {
+        let normal = self.normal.normalize_or(Vec3::Y);
+        Transform::from_translation(self.point)
+            .with_rotation(Quat::from_rotation_arc(*self.up, normal))
+    }
+}
+
+/// Which parts are attached to what. Sockets live on the parts,
+/// this is the index going the other way.
+#[derive(Resource, Default)]
+pub struct SocketGraph {
+    parents: HashMap<Entity, Entity>,
+    attached: HashMap<Entity, Vec<Entity>>,
+}
+
+impl SocketGraph {
+    pub fn parent(&self, part: Entity) -> Option<Entity> {
+        self.parents.get(&part).copied()
+    }
+
+    pub fn attached_to(&self, parent: Entity) -> &[Entity] {
+        self.attached.get(&parent).map_or(&[], |v| v.as_slice())
+    }
+
+    fn link(&mut self, part: Entity, parent: Entity) {
+        self.unlink(part);
+        self.parents.insert(part, parent);
+        self.attached.entry(parent).or_default().push(part);
+    }
+
+    fn unlink(&mut self, part: Entity) {
+        let Some(parent) = self.parents.remove(&part) else {
+            return;
+        };
+        if let Some(parts) = self.attached.get_mut(&parent) {
+            parts.retain(|e| *e != part);
+            if parts.is_empty() {
+                self.attached.remove(&parent);
+            }
+        }
+    }
+}
+
+/// Attach the target part to a mesh. `point` and `normal` are in the mesh's local space.
+#[derive(Debug, Event)]
+pub struct AttachPart {
+    pub parent: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub up: Dir3,
+}
+
+/// Take the target part off whatever it's attached to, leaving it where it is in the world
+#[derive(Debug, Event)]
+pub struct DetachPart;
+
+impl Plugin for SocketPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SocketGraph>();
+        app.add_systems(PostUpdate, prune_sockets);
+        app.add_observer(attach_part);
+        app.add_observer(detach_part);
+    }
+}
+
+fn attach_part(
+    trigger: Trigger<AttachPart>,
+    transforms: Query<&Transform>,
+    mut graph: ResMut<SocketGraph>,
+    mut commands: Commands,
+) {
+    let part = trigger.entity();
+    let event = trigger.event();
+    if commands.get_entity(event.parent).is_none() {
+        return;
+    }
+
+    let socket = Socket {
+        parent: event.parent,
+        point: event.point,
+        normal: event.normal,
+        up: event.up,
+    };
+    graph.link(part, event.parent);
+
+    // Parts keep their own size wherever they go
+    let scale = transforms.get(part).map_or(Vec3::ONE, |t| t.scale);
+    commands
+        .entity(part)
+        .remove::<BodyRoot>()
+        .insert((socket, socket.transform().with_scale(scale)))
+        .set_parent(event.parent);
+}
+
+fn detach_part(
+    trigger: Trigger<DetachPart>,
+    mut graph: ResMut<SocketGraph>,
+    mut commands: Commands,
+) {
+    let part = trigger.entity();
+    if graph.parent(part).is_none() {
+        return;
+    }
+    graph.unlink(part);
+
+    commands
+        .entity(part)
+        .remove::<Socket>()
+        .remove_parent_in_place()
+        .insert(BodyRoot);
+}
+
+/// Forget about parts that were despawned while still attached
+fn prune_sockets(
+    mut removed: RemovedComponents<Socket>,
+    sockets: Query<(), With<Socket>>,
+    mut graph: ResMut<SocketGraph>,
+) {
+    for part in removed.read() {
+        // Moved somewhere else rather than gone
+        if sockets.contains(part) {
+            continue;
+        }
+        graph.unlink(part);
+    }
+}
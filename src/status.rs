@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+
+use crate::interact::InteractLayers;
+use crate::inventory::ItemId;
+use crate::person::{Health, KillPerson, Person};
+use crate::player::{hand, Player};
+use crate::tool::{Tool, ToolAppExt, ToolTarget, ToolUse, UseUpItem, Viewmodel};
+use crate::townsfolk::{TownsfolkTask, TownsfolkTaskType};
+
+pub struct StatusPlugin;
+
+/// A hit at least this hard knocks the wind out of whoever it lands on
+const STUN_POWER: f32 = 15.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+    Poisoned,
+    Stunned,
+    Burning,
+    Bleeding,
+    Charmed,
+}
+
+/// What happens when an effect lands on someone who already has it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stacking {
+    /// Keep the strongest, and the longest time left
+    Refresh,
+    /// Add the strengths up to a cap, and take the longest time left
+    Intensify { max: f32 },
+}
+
+impl StatusKind {
+    pub fn stacking(&self) -> Stacking {
+        match *self {
+            Self::Poisoned => Stacking::Intensify { max: 10.0 },
+            Self::Bleeding => Stacking::Intensify { max: 20.0 },
+            Self::Stunned | Self::Burning | Self::Charmed => Stacking::Refresh,
+        }
+    }
+
+    /// Health per second lost per point of strength
+    pub fn damage(&self) -> f32 {
+        match *self {
+            Self::Poisoned | Self::Burning | Self::Bleeding => 1.0,
+            Self::Stunned | Self::Charmed => 0.0,
+        }
+    }
+
+    /// Multiplier on how fast whoever has it can move
+    pub fn speed(&self) -> f32 {
+        match *self {
+            Self::Stunned => 0.0,
+            Self::Poisoned => 0.6,
+            // Running round on fire
+            Self::Burning => 1.5,
+            Self::Bleeding | Self::Charmed => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub strength: f32,
+    /// Seconds left, or `None` to last until something removes it
+    pub remaining: Option<f32>,
+}
+
+impl StatusEffect {
+    pub fn timed(kind: StatusKind, strength: f32, secs: f32) -> Self {
+        Self { kind, strength, remaining: Some(secs) }
+    }
+}
+
+#[derive(Debug, Clone, Default, Component)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.0.iter().find(|e| e.kind == kind)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let Some(existing) = self.0.iter_mut().find(|e| e.kind == effect.kind) else {
+            self.0.push(effect);
+            return;
+        };
+        existing.strength = match effect.kind.stacking() {
+            Stacking::Refresh => existing.strength.max(effect.strength),
+            Stacking::Intensify { max } => (existing.strength + effect.strength).min(max),
+        };
+        existing.remaining = match (existing.remaining, effect.remaining) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
+        };
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.0.iter().map(|e| e.kind.speed()).product()
+    }
+
+    fn damage(&self) -> f32 {
+        self.0.iter().map(|e| e.kind.damage() * e.strength).sum()
+    }
+}
+
+/// Put a status effect on the targeted person or player
+#[derive(Debug, Event)]
+pub struct ApplyStatus(pub StatusEffect);
+
+/// The effects an item leaves behind on whatever it hits
+pub fn item_statuses(item_id: ItemId, power: f32) -> Vec<StatusEffect> {
+    let mut effects = match item_id {
+        // Wounds clot, given time
+        ItemId::Sword => vec![StatusEffect::timed(StatusKind::Bleeding, 1.0, 10.0)],
+        ItemId::Gun => vec![
+            StatusEffect::timed(StatusKind::Bleeding, 0.5, 6.0),
+            StatusEffect::timed(StatusKind::Burning, 1.0, 2.0),
+        ],
+        _ => vec![],
+    };
+    if power >= STUN_POWER {
+        effects.push(StatusEffect::timed(StatusKind::Stunned, 1.0, 1.5));
+    }
+    effects
+}
+
+/// What eating one of the player's apples does. A poisoned apple is
+/// still a gift.
+fn apple_statuses() -> [StatusEffect; 2] {
+    [
+        StatusEffect::timed(StatusKind::Poisoned, 2.0, 8.0),
+        StatusEffect::timed(StatusKind::Charmed, 1.0, 8.0),
+    ]
+}
+
+/// Hands someone an apple, which they eat
+struct AppleTool;
+
+impl Tool for AppleTool {
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse) {
+        let Some(person) = ctx.target.and_then(|t| t.person) else {
+            return;
+        };
+        for effect in apple_statuses() {
+            commands.trigger_targets(ApplyStatus(effect), person);
+        }
+        commands.trigger_targets(UseUpItem { item_id: ItemId::Apple, num: 1 }, ctx.user);
+    }
+
+    fn reach(&self) -> f32 {
+        2.5
+    }
+
+    fn verb(&self, target: &ToolTarget) -> Option<String> {
+        target.person.map(|_| "give an apple".to_string())
+    }
+
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::PERSON
+    }
+
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        Some(hand())
+    }
+}
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_tool(ItemId::Apple, AppleTool);
+        app.add_systems(Update, (
+            tick_statuses,
+            status_behaviour
+        ));
+        app.add_observer(apply_status);
+    }
+}
+
+fn apply_status(
+    trigger: Trigger<ApplyStatus>,
+    mut q: Query<Option<&mut StatusEffects>, With<Health>>,
+    mut commands: Commands,
+) {
+    let e = trigger.entity();
+    let effect = trigger.event().0;
+    let Ok(effects) = q.get_mut(e) else {
+        return;
+    };
+    info!("{:?} now {:?}", e, effect.kind);
+    match effects {
+        Some(mut effects) => effects.apply(effect),
+        None => {
+            commands.entity(e).insert(StatusEffects(vec![effect]));
+        }
+    }
+}
+
+fn tick_statuses(
+    time: Res<Time>,
+    mut q: Query<(Entity, &mut StatusEffects, &mut Health, Has<Person>)>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_secs();
+    for (e, mut effects, mut health, is_person) in q.iter_mut() {
+        if health.0 <= 0.0 {
+            continue;
+        }
+
+        health.0 -= effects.damage() * dt;
+        if health.0 <= 0.0 {
+            info!("{:?} succumbed to {:?}", e, effects.0.iter().map(|e| e.kind).collect::<Vec<_>>());
+            if is_person {
+                commands.entity(e).remove::<StatusEffects>();
+                commands.trigger_targets(KillPerson::default(), e);
+            }
+            continue;
+        }
+
+        for effect in effects.0.iter_mut() {
+            if let Some(t) = effect.remaining.as_mut() {
+                *t -= dt;
+            }
+        }
+        effects.0.retain(|e| e.remaining.is_none_or(|t| t > 0.0));
+    }
+}
+
+/// Burning townsfolk panic, charmed ones forget their fear and come to the player
+fn status_behaviour(
+    mut folk: Query<(&mut Transform, &StatusEffects, &mut TownsfolkTask), (With<Person>, Without<Player>)>,
+    player: Query<&Transform, With<Player>>,
+) {
+    let player = player.get_single().ok().map(|t| t.translation);
+    for (mut t, effects, mut task) in folk.iter_mut() {
+        if effects.has(StatusKind::Burning) {
+            if !matches!(task.task, TownsfolkTaskType::Fleaing) {
+                task.task = TownsfolkTaskType::Fleaing;
+            }
+            continue;
+        }
+        if effects.has(StatusKind::Charmed) {
+            task.task = TownsfolkTaskType::Idle;
+            if let Some(p) = player {
+                let to = (p - t.translation) * Vec3::new(1.0, 0.0, 1.0);
+                if to.length() > 2.0 {
+                    t.look_to(to.normalize(), Vec3::Y);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use bevy::{prelude::*, render::mesh::VertexAttributeValues};
 use noise::{NoiseFn, Perlin, BasicMulti};
 use crate::person::{Pickable};
+use crate::interact::InteractLayers;
+use crate::gltf_tags::{GltfTagAppExt, NamePattern};
+use std::sync::LazyLock;
+
+static NOISE: LazyLock<BasicMulti<Perlin>> = LazyLock::new(BasicMulti::<Perlin>::default);
 
 #[derive(Component)]
 pub struct Terrain;
@@ -15,6 +20,9 @@ impl Plugin for TerrainPlugin {
         app.insert_resource(GreetTimer(Timer::from_seconds(2.0, TimerMode::Repeating)));
         app.add_systems(Startup, setup);
         app.add_systems(Update, greet_terrain);
+        app.register_gltf_tag(NamePattern::suffix("Floor"), |e, _| {
+            e.insert((Terrain, Pickable, InteractLayers::TERRAIN));
+        });
     }
 }
 
@@ -27,7 +35,20 @@ fn greet_terrain(time: Res<Time>, mut timer: ResMut<GreetTimer>, _query: Query<&
 fn dist(x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
     let dx = x2 - x1;
     let dy = y2 - y1;
-    return (dx * dx + dy * dy).sqrt();
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Height of the ground at a world position. Flat in the middle of
+/// town, rolling noise hills further out.
+pub fn height_at(x: f32, z: f32) -> f32 {
+    let terrain_height = 70.;
+    let val = NOISE.get([
+        x as f64 / 300.0,
+        z as f64 / 300.0
+    ]);
+    let d = dist(0 as f64, 0 as f64, x as f64, z as f64);
+    let mult = ((d - 50.0) / 50.0).clamp(0.0, 1.0);
+    (val * mult) as f32 * terrain_height
 }
 
 fn setup(
@@ -51,17 +72,8 @@ fn setup(
     if let Some(VertexAttributeValues::Float32x3(
         positions,
     )) = terrain.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
-        let terrain_height = 70.;
-        let noise = BasicMulti::<Perlin>::default();
-
         for pos in positions.iter_mut() {
-            let val = noise.get([
-                pos[0] as f64 / 300.0,
-                pos[2] as f64 / 300.0
-            ]);
-            let d = dist(0 as f64, 0 as f64, pos[0] as f64, pos[2] as f64);
-            let mult = ((d - 50.0) / 50.0).clamp(0.0, 1.0);
-            pos[1] = (val * mult) as f32 * terrain_height;
+            pos[1] = height_at(pos[0], pos[2]);
         }
         terrain.compute_normals();
     }
@@ -70,5 +82,7 @@ fn setup(
         Mesh3d(meshes.add(terrain)),
         mat,
         Terrain,
+        Pickable,
+        InteractLayers::TERRAIN,
     ));
 }
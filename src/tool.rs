@@ -0,0 +1,354 @@
+use bevy::prelude::*;
+use bevy::pbr::NotShadowCaster;
+use std::collections::HashMap;
+
+use crate::actions::{Action, ActionState};
+use crate::camera::CameraMode;
+use crate::carry::Carrying;
+use crate::hotbar::HotbarSelected;
+use crate::interact::InteractLayers;
+use crate::inventory::{Inventory, ItemId};
+use crate::person::Person;
+use crate::player::{Player, RaycastTarget};
+use crate::vitals::Dead;
+
+pub struct ToolPlugin;
+
+/// The scene shown in front of the camera while a tool is selected
+#[derive(Debug, Clone)]
+pub struct Viewmodel {
+    pub scene: &'static str,
+    pub transform: Transform,
+}
+
+/// What's being aimed at, when it's within reach
+#[derive(Debug, Clone, Copy)]
+pub struct ToolTarget {
+    pub mesh: Entity,
+    /// Top of the hierarchy the mesh is in
+    pub root: Entity,
+    /// The person the mesh belongs to, if any
+    pub person: Option<Entity>,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// `point` and `normal` in the mesh's local space
+    pub mesh_point: Vec3,
+    pub mesh_normal: Vec3,
+}
+
+/// Everything a tool gets to know when it's used
+#[derive(Debug, Clone)]
+pub struct ToolUse {
+    pub user: Entity,
+    pub item_id: ItemId,
+    pub dir: Dir3,
+    pub target: Option<ToolTarget>,
+    /// How far a charged action was wound up, from 0 to 1
+    pub charge: f32,
+}
+
+pub trait Tool: Send + Sync + 'static {
+    fn primary(&self, commands: &mut Commands, ctx: &ToolUse);
+
+    fn secondary(&self, _commands: &mut Commands, _ctx: &ToolUse) {}
+
+    /// Seconds before the tool can be used again
+    fn cooldown(&self) -> f32 {
+        0.2
+    }
+
+    /// How far away things can be and still get hit
+    fn reach(&self) -> f32 {
+        6.0
+    }
+
+    /// What it can be aimed at. Anything else is seen through.
+    fn layers(&self) -> InteractLayers {
+        InteractLayers::ALL
+    }
+
+    fn viewmodel(&self) -> Option<Viewmodel> {
+        None
+    }
+
+    /// Seconds to wind up fully. Charged tools use their primary when
+    /// the button is let go, rather than when it's pressed.
+    fn charge_time(&self) -> Option<f32> {
+        None
+    }
+
+    /// What the primary would do to `target`, for the tooltip
+    fn verb(&self, _target: &ToolTarget) -> Option<String> {
+        None
+    }
+}
+
+/// Tools by the item they're used with. Empty hands, and items with
+/// no tool of their own, use the `Fist`.
+#[derive(Resource, Default)]
+pub struct ToolRegistry {
+    tools: HashMap<ItemId, Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn register(&mut self, item_id: ItemId, tool: impl Tool) {
+        self.tools.insert(item_id, Box::new(tool));
+    }
+
+    pub fn get(&self, item_id: ItemId) -> Option<&dyn Tool> {
+        self.tools.get(&item_id).map(|t| t.as_ref())
+    }
+
+    /// The item whose tool is used for `item_id`
+    fn resolve(&self, item_id: Option<ItemId>) -> ItemId {
+        item_id
+            .filter(|id| self.tools.contains_key(id))
+            .unwrap_or(ItemId::Fist)
+    }
+}
+
+pub trait ToolAppExt {
+    /// Use `tool` whenever `item_id` is selected in the hotbar
+    fn register_tool(&mut self, item_id: ItemId, tool: impl Tool) -> &mut Self;
+}
+
+impl ToolAppExt for App {
+    fn register_tool(&mut self, item_id: ItemId, tool: impl Tool) -> &mut Self {
+        self.init_resource::<ToolRegistry>();
+        self.world_mut()
+            .resource_mut::<ToolRegistry>()
+            .register(item_id, tool);
+        self
+    }
+}
+
+/// Use up some of an item from the targeted user's inventory
+#[derive(Debug, Event)]
+pub struct UseUpItem {
+    pub item_id: ItemId,
+    pub num: u32,
+}
+
+/// The viewmodel entity in front of the camera
+#[derive(Component)]
+pub struct ToolViz;
+
+/// The player's side of using tools
+#[derive(Component, Default)]
+pub struct ToolState {
+    /// Item whose tool is in hand
+    pub item_id: Option<ItemId>,
+    viewmodel: Option<Entity>,
+    pub cooldown: f32,
+    /// How long the primary has been held, while charging
+    pub held: Option<f32>,
+}
+
+impl ToolState {
+    /// Whether the primary goes off this frame, and how far it was wound
+    /// up if so. Charged tools go off when let go, the rest when pressed.
+    fn primary(&mut self, charge_time: Option<f32>, pressed: bool, released: bool, dt: f32) -> Option<f32> {
+        if let Some(held) = self.held.as_mut() {
+            *held += dt;
+        }
+        if self.cooldown > 0.0 {
+            return None;
+        }
+        let Some(charge_time) = charge_time else {
+            return pressed.then_some(0.0);
+        };
+        if pressed {
+            self.held = Some(0.0);
+        }
+        if !released {
+            return None;
+        }
+        self.held.take().map(|held| (held / charge_time.max(0.01)).min(1.0))
+    }
+}
+
+impl Plugin for ToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ToolRegistry>();
+        app.add_systems(Update, (
+            swap_viewmodel,
+            use_tool
+        ).chain());
+        app.add_observer(use_up_item);
+    }
+}
+
+fn use_up_item(
+    trigger: Trigger<UseUpItem>,
+    mut users: Query<&mut Inventory>,
+) {
+    let Ok(mut inv) = users.get_mut(trigger.entity()) else {
+        return;
+    };
+    let event = trigger.event();
+    let used = inv.remove(event.item_id, event.num);
+    info!("used {} {:?}, {} left", used, event.item_id, inv.count(event.item_id));
+}
+
+/// The tool's view of what the player is aiming at
+pub fn tool_target(
+    ray_target: &RaycastTarget,
+    parent_q: &Query<&Parent>,
+    persons: &Query<(), With<Person>>,
+) -> Option<ToolTarget> {
+    let (mesh, point) = ray_target.mesh.zip(ray_target.point)?;
+    let root = parent_q.root_ancestor(mesh);
+    Some(ToolTarget {
+        mesh,
+        root,
+        person: persons.contains(root).then_some(root),
+        point,
+        normal: ray_target.normal,
+        mesh_point: ray_target.mesh_point,
+        mesh_normal: ray_target.mesh_normal,
+    })
+}
+
+fn selected_item(
+    hotbar: &Query<&HotbarSelected>,
+    inv: &Inventory,
+) -> Option<ItemId> {
+    let selected = hotbar.get_single().ok()?.0;
+    inv.map.get(&selected).map(|s| s.item_id)
+}
+
+/// Put the selected item's viewmodel in the player's hand
+fn swap_viewmodel(
+    registry: Res<ToolRegistry>,
+    hotbar: Query<&HotbarSelected>,
+    mut player: Query<(Entity, &Inventory, &mut ToolState), With<Player>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let Ok((player, inv, mut state)) = player.get_single_mut() else {
+        return;
+    };
+    let item_id = registry.resolve(selected_item(&hotbar, inv));
+    if state.item_id == Some(item_id) {
+        return;
+    }
+
+    state.item_id = Some(item_id);
+    state.held = None;
+    if let Some(old) = state.viewmodel.take() {
+        commands.entity(old).despawn_recursive();
+    }
+    let Some(viewmodel) = registry.get(item_id).and_then(|t| t.viewmodel()) else {
+        return;
+    };
+    let e = commands.spawn((
+        Name::new(format!("{:?} viewmodel", item_id)),
+        ToolViz,
+        SceneRoot(
+            asset_server
+                .load(GltfAssetLabel::Scene(0).from_asset(viewmodel.scene))),
+        viewmodel.transform,
+        NotShadowCaster
+    )).id();
+    commands.entity(player).add_child(e);
+    state.viewmodel = Some(e);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn use_tool(
+    time: Res<Time>,
+    registry: Res<ToolRegistry>,
+    ray_target: Res<RaycastTarget>,
+    actions: Res<ActionState>,
+    camera: Res<CameraMode>,
+    mut player: Query<(Entity, &mut ToolState, &Carrying), (With<Player>, Without<Dead>)>,
+    parent_q: Query<&Parent>,
+    persons: Query<(), With<Person>>,
+    mut commands: Commands,
+) {
+    let Ok((user, mut state, carrying)) = player.get_single_mut() else {
+        return;
+    };
+    state.cooldown = (state.cooldown - time.delta_secs()).max(0.0);
+
+    // Hands are full, or we're off flying round
+    if carrying.entity.is_some() || !camera.controls_player() {
+        state.held = None;
+        return;
+    }
+    let Some(item_id) = state.item_id else {
+        return;
+    };
+    let Some(tool) = registry.get(item_id) else {
+        return;
+    };
+
+    // Aiming reaches further than some tools do
+    let target = tool_target(&ray_target, &parent_q, &persons)
+        .filter(|_| ray_target.distance <= tool.reach());
+    let mut ctx = ToolUse {
+        user,
+        item_id,
+        dir: ray_target.dir,
+        target,
+        charge: 0.0,
+    };
+
+    let ready = state.cooldown <= 0.0;
+    let fired = state.primary(
+        tool.charge_time(),
+        actions.just_pressed(Action::Use),
+        actions.just_released(Action::Use),
+        time.delta_secs(),
+    );
+    if let Some(charge) = fired {
+        ctx.charge = charge;
+        tool.primary(&mut commands, &ctx);
+        state.cooldown = tool.cooldown();
+    }
+
+    if ready && actions.just_pressed(Action::AltUse) {
+        tool.secondary(&mut commands, &ctx);
+        state.cooldown = tool.cooldown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.1;
+
+    #[test]
+    fn uncharged_tools_go_off_when_pressed() {
+        let mut state = ToolState::default();
+        assert_eq!(state.primary(None, true, false, DT), Some(0.0));
+        assert_eq!(state.primary(None, false, true, DT), None);
+        state.cooldown = 0.5;
+        assert_eq!(state.primary(None, true, false, DT), None);
+    }
+
+    #[test]
+    fn charged_tools_go_off_when_let_go() {
+        let mut state = ToolState::default();
+        assert_eq!(state.primary(Some(1.0), true, false, DT), None);
+        for _ in 0..4 {
+            assert_eq!(state.primary(Some(1.0), false, false, DT), None);
+        }
+        let charge = state.primary(Some(1.0), false, true, DT).unwrap();
+        assert!((charge - 0.5).abs() < 1e-4, "{}", charge);
+        assert!(state.held.is_none());
+        // Letting go again without pressing does nothing
+        assert_eq!(state.primary(Some(1.0), false, true, DT), None);
+    }
+
+    #[test]
+    fn charge_stops_at_full() {
+        let mut state = ToolState::default();
+        state.primary(Some(0.2), true, false, DT);
+        for _ in 0..10 {
+            state.primary(Some(0.2), false, false, DT);
+        }
+        assert_eq!(state.primary(Some(0.2), false, true, DT), Some(1.0));
+    }
+}
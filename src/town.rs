@@ -1,5 +1,9 @@
 use crate::terrain::TerrainPlugin;
 use crate::person::Pickable;
+use crate::status::{StatusEffect, StatusKind};
+use crate::terrain::height_at;
+use crate::vitals::{Bed, Hazard, Home};
+use crate::interact::InteractLayers;
 
 use rand::prelude::*;
 
@@ -9,6 +13,10 @@ use bevy::prelude::*;
 
 pub struct TownPlugin;
 
+/// Something built, that the camera can't go through
+#[derive(Component)]
+pub struct Building;
+
 impl Plugin for TownPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(TerrainPlugin);
@@ -31,7 +39,7 @@ fn setup(
                 asset_server
                     .load(GltfAssetLabel::Scene(0).from_asset("banart.glb"))),
             Transform::from_xyz(10.0, -0.2, 20.0)
-                .with_rotation(Quat::from_rotation_y((-PI / 2.)))
+                .with_rotation(Quat::from_rotation_y(-PI / 2.))
                 .with_scale(Vec3::splat(20.0))
         ));
 
@@ -43,7 +51,7 @@ fn setup(
                 asset_server
                     .load(GltfAssetLabel::Scene(0).from_asset("rocks.glb"))),
             Transform::from_xyz(20.0, -0.2, 10.0)
-                .with_rotation(Quat::from_rotation_y((-PI / 2.)))
+                .with_rotation(Quat::from_rotation_y(-PI / 2.))
                 .with_scale(Vec3::splat(20.0))
         ));
 
@@ -52,6 +60,8 @@ fn setup(
     commands
         .spawn((
             Name::new("building1"),
+            Building,
+            InteractLayers::BUILDING,
             SceneRoot(
                 asset_server
                     .load(GltfAssetLabel::Scene(0).from_asset("building1.glb"))),
@@ -63,6 +73,8 @@ fn setup(
     commands
         .spawn((
             Name::new("building2"),
+            Building,
+            InteractLayers::BUILDING,
             SceneRoot(
                 asset_server
                     .load(GltfAssetLabel::Scene(0).from_asset("building1.glb"))),
@@ -75,6 +87,9 @@ fn setup(
     commands
         .spawn((
             Name::new("home"),
+            Building,
+            InteractLayers::BUILDING,
+            Home,
             SceneRoot(
                 asset_server
                     .load(GltfAssetLabel::Scene(0).from_asset("home.glb"))),
@@ -106,6 +121,35 @@ fn setup(
         ..default()
     }));
 
+    commands.spawn((
+        Name::new("Campfire"),
+        Hazard {
+            radius: 1.2,
+            damage: 15.0,
+            status: Some(StatusEffect::timed(StatusKind::Burning, 1.0, 3.0)),
+        },
+        Mesh3d(meshes.add(Cylinder::new(0.6, 0.3))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Srgba::hex("#ff6622").unwrap().into(),
+            emissive: LinearRgba::rgb(4.0, 1.0, 0.2),
+            ..default()
+        })),
+        Transform::from_xyz(-10.0, height_at(-10.0, 30.0) + 0.15, 30.0),
+    ));
+
+    // Out by the front of the house, to sleep in
+    commands.spawn((
+        Name::new("Bed"),
+        Bed,
+        Pickable,
+        Mesh3d(meshes.add(Cuboid::new(1.0, 0.5, 2.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Srgba::hex("#6a4a7a").unwrap().into(),
+            ..default()
+        })),
+        Transform::from_xyz(4.0, height_at(4.0, 36.0) + 0.25, 36.0),
+    ));
+
     commands.spawn((
         Name::new("Test"),
         Pickable,
@@ -1,7 +1,21 @@
 use bevy::prelude::*;
 
+use crate::crowd::SimBudget;
+use crate::limb::Capabilities;
+use crate::person::{HitBodyPart, Person, Speed};
+use crate::status::{StatusEffects, StatusKind};
+use crate::vitals::{Dead, Hurt, HurtCause};
+
 pub struct TownsfolkPlugin;
 
+/// How close they get before they can hit
+const ATTACK_REACH: f32 = 1.5;
+const ATTACK_DAMAGE: f32 = 10.0;
+/// Seconds between hits
+const ATTACK_COOLDOWN: f32 = 1.0;
+/// They run faster than they wander when they're after someone
+const CHASE_SPEED: f32 = 3.0;
+
 #[derive(Component)]
 pub struct LookingForWork;
 
@@ -10,6 +24,8 @@ pub enum TownsfolkTaskType {
     Idle,
     Wandering(Vec3),
     Fleaing,
+    /// Going after whoever they're `Hostile` to
+    Attacking,
 }
 
 #[derive(Component)]
@@ -17,9 +33,27 @@ pub struct TownsfolkTask {
     pub task: TownsfolkTaskType
 }
 
+/// Out to get someone who hurt them
+#[derive(Component)]
+pub struct Hostile {
+    pub target: Entity,
+    /// Seconds until they can hit again
+    cooldown: f32,
+}
+
+impl Hostile {
+    pub fn new(target: Entity) -> Self {
+        Self { target, cooldown: 0.0 }
+    }
+}
+
 impl Plugin for TownsfolkPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, schedule_task);
+        app.add_systems(Update, (
+            schedule_task,
+            chase_and_attack
+        ));
+        app.add_observer(provoke);
     }
 }
 
@@ -35,9 +69,77 @@ fn schedule_task(
 }
 
 
-fn init_task(
+/// Whoever hits someone makes an enemy of them
+fn provoke(
+    trigger: Trigger<HitBodyPart>,
+    parent_q: Query<&Parent>,
+    mut folk: Query<&mut TownsfolkTask, With<Capabilities>>,
     mut commands: Commands,
-    q: Query<Entity, Added<TownsfolkTask>>
-){
-    //
+) {
+    let Some(by) = trigger.event().by else {
+        return;
+    };
+    let root = parent_q.root_ancestor(trigger.entity());
+    if root == by {
+        return;
+    }
+    let Ok(mut task) = folk.get_mut(root) else {
+        return;
+    };
+    info!("{:?} wants revenge on {:?}", root, by);
+    task.task = TownsfolkTaskType::Attacking;
+    commands.entity(root).insert(Hostile::new(by));
+}
+
+/// Run at the target and hit them once close enough. They give up once
+/// the target is dead, gone, or out of sight.
+fn chase_and_attack(
+    time: Res<Time>,
+    mut folk: Query<(Entity, &mut Transform, &Speed, &Capabilities, &mut TownsfolkTask, &mut Hostile, Option<&SimBudget>, Option<&StatusEffects>), With<Person>>,
+    targets: Query<(&GlobalTransform, Has<Dead>)>,
+    mut commands: Commands,
+) {
+    for (e, mut t, speed, caps, mut task, mut hostile, budget, effects) in folk.iter_mut() {
+        let dt = match budget {
+            Some(b) if !b.ready => continue,
+            Some(b) => b.dt,
+            None => time.delta_secs()
+        };
+        hostile.cooldown = (hostile.cooldown - dt).max(0.0);
+
+        let target = targets.get(hostile.target).ok().filter(|(_, dead)| !dead);
+        let to = target.map(|(gt, _)| (gt.translation() - t.translation) * Vec3::new(1.0, 0.0, 1.0));
+        let Some(to) = to.filter(|to| to.length() < caps.perception * 3.0) else {
+            info!("{:?} gives up", e);
+            task.task = TownsfolkTaskType::Idle;
+            commands.entity(e).remove::<Hostile>();
+            continue;
+        };
+
+        // Burning or charmed takes priority over a grudge
+        if effects.is_some_and(|e| e.has(StatusKind::Burning) || e.has(StatusKind::Charmed)) {
+            continue;
+        }
+        task.task = TownsfolkTaskType::Attacking;
+        if to.length() > 0.01 {
+            t.look_to(to.normalize(), Vec3::Y);
+        }
+
+        if to.length() > ATTACK_REACH {
+            if caps.can_walk {
+                let run = speed.0 * caps.speed * effects.map_or(1.0, |e| e.speed()) * CHASE_SPEED;
+                t.translation += to.normalize() * run * dt;
+            }
+            continue;
+        }
+        // Can't hit anything without arms, or while seeing stars
+        if hostile.cooldown > 0.0 || !caps.can_carry || effects.is_some_and(|e| e.has(StatusKind::Stunned)) {
+            continue;
+        }
+        hostile.cooldown = ATTACK_COOLDOWN;
+        commands.trigger_targets(
+            Hurt { amount: ATTACK_DAMAGE, cause: HurtCause::Attack(e) },
+            hostile.target
+        );
+    }
 }
@@ -1,8 +1,4 @@
 use bevy::prelude::*;
-use crate::player::Player;
-
-#[derive(Component)]
-struct Ui;
 
 pub struct UiPlugin;
 
@@ -13,11 +9,7 @@ impl Plugin for UiPlugin {
 }
 
 fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>) {
-
-    let mesh = meshes.add(Sphere::default().mesh().uv(12, 8));
+    mut commands: Commands) {
 
     commands.spawn((
         Name::new("Crosshair"),
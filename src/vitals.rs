@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::actions::{Action, ActionState};
+use crate::carry::{Carrying, DropCarried};
+use crate::controller::{CharacterController, Landed};
+use crate::gltf_tags::{GltfTagAppExt, NamePattern};
+use crate::inventory::{Inventory, ItemId};
+use crate::person::{Health, KillPerson, Person};
+use crate::pickup::SpawnItem;
+use crate::player::{Player, RaycastTarget};
+use crate::status::{ApplyStatus, StatusEffect, StatusEffects};
+
+pub struct VitalsPlugin;
+
+pub const PLAYER_HEALTH: f32 = 100.0;
+/// Landing slower than this doesn't hurt, about a four metre drop
+const SAFE_FALL_SPEED: f32 = 9.0;
+/// Health lost per m/s of landing speed over the safe speed
+const FALL_DAMAGE: f32 = 8.0;
+/// Seconds for the screen to go dark, or come back
+const FADE_SECS: f32 = 1.5;
+/// Seconds dead before coming back
+const RESPAWN_SECS: f32 = 3.0;
+/// Where to wake up, relative to home
+const HOME_SPAWN: Vec3 = Vec3::new(0.0, 1.0, -6.0);
+/// How hard everything gets scattered on dying
+const DEATH_SCATTER: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy)]
+pub enum HurtCause {
+    /// Hit by someone
+    Attack(Entity),
+    Fall,
+    Hazard,
+}
+
+/// Take health off the targeted person or player
+#[derive(Debug, Event)]
+pub struct Hurt {
+    pub amount: f32,
+    pub cause: HurtCause,
+}
+
+/// Hurts anyone standing in it
+#[derive(Component)]
+pub struct Hazard {
+    pub radius: f32,
+    /// Health per second taken from the player
+    pub damage: f32,
+    /// Put on anyone in it, player or not
+    pub status: Option<StatusEffect>,
+}
+
+/// The player has died, and is waiting to come back
+#[derive(Component)]
+pub struct Dead {
+    /// Seconds since dying
+    t: f32,
+}
+
+/// Somewhere to sleep, which becomes where the player wakes up
+#[derive(Component)]
+pub struct Bed;
+
+/// The player's house, where they wake up if they haven't slept anywhere
+#[derive(Component)]
+pub struct Home;
+
+/// The last bed slept in
+#[derive(Resource, Default)]
+pub struct SpawnPoint(pub Option<Vec3>);
+
+#[derive(Component)]
+struct Fade;
+
+#[derive(Component)]
+struct HealthText;
+
+impl Plugin for VitalsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnPoint>();
+        app.add_systems(Startup, setup);
+        app.add_systems(Update, (
+            hazards,
+            die,
+            respawn,
+            fade,
+            sleep_in_bed,
+            show_health
+        ));
+        app.add_observer(hurt);
+        app.add_observer(fall_damage);
+        app.register_gltf_tag(NamePattern::prefix("Bed"), |e, _| {
+            e.insert(Bed);
+        });
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        Name::new("Fade"),
+        Fade,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.0)),
+        GlobalZIndex(10),
+    ));
+
+    commands.spawn((
+        Name::new("Health"),
+        HealthText,
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(12.0),
+            top: Val::Px(12.0),
+            ..default()
+        },
+        Text::new(""),
+    ));
+}
+
+fn hurt(
+    trigger: Trigger<Hurt>,
+    mut q: Query<(&mut Health, Has<Person>), Without<Dead>>,
+    mut commands: Commands,
+) {
+    let e = trigger.entity();
+    let Ok((mut health, is_person)) = q.get_mut(e) else {
+        return;
+    };
+    if health.0 <= 0.0 {
+        return;
+    }
+    let event = trigger.event();
+    health.0 -= event.amount;
+    info!("{:?} hurt {:.1} by {:?}, {:.0} left", e, event.amount, event.cause, health.0);
+    if health.0 <= 0.0 && is_person {
+        commands.trigger_targets(KillPerson::default(), e);
+    }
+}
+
+fn fall_damage(
+    trigger: Trigger<Landed>,
+    players: Query<(), With<Player>>,
+    mut commands: Commands,
+) {
+    let e = trigger.entity();
+    let over = trigger.event().speed - SAFE_FALL_SPEED;
+    if over > 0.0 && players.contains(e) {
+        commands.trigger_targets(Hurt { amount: over * FALL_DAMAGE, cause: HurtCause::Fall }, e);
+    }
+}
+
+fn hazards(
+    time: Res<Time>,
+    hazards: Query<(&GlobalTransform, &Hazard)>,
+    victims: Query<(Entity, &GlobalTransform, Has<Player>), (With<Health>, Without<Dead>)>,
+    mut commands: Commands,
+) {
+    let dt = time.delta_secs();
+    for (ht, hazard) in hazards.iter() {
+        for (e, t, is_player) in victims.iter() {
+            if t.translation().distance(ht.translation()) > hazard.radius {
+                continue;
+            }
+            if is_player && hazard.damage > 0.0 {
+                commands.trigger_targets(Hurt { amount: hazard.damage * dt, cause: HurtCause::Hazard }, e);
+            }
+            if let Some(status) = hazard.status {
+                commands.trigger_targets(ApplyStatus(status), e);
+            }
+        }
+    }
+}
+
+/// Out of health: scatter everything but bare hands on the ground and go dark
+fn die(
+    mut player: Query<(Entity, &Transform, &Health, &mut Inventory, &mut CharacterController, &Carrying), (With<Player>, Without<Dead>)>,
+    mut commands: Commands,
+) {
+    let Ok((e, t, health, mut inv, mut controller, carrying)) = player.get_single_mut() else {
+        return;
+    };
+    if health.0 > 0.0 {
+        return;
+    }
+    info!("You died");
+    controller.wish = Vec3::ZERO;
+    if carrying.entity.is_some() {
+        commands.trigger(DropCarried { power: 0.0 });
+    }
+    commands.entity(e).insert(Dead { t: 0.0 });
+
+    let mut slots: Vec<u32> = inv.map
+        .iter()
+        .filter(|(_, s)| s.item_id != ItemId::Fist)
+        .map(|(slot, _)| *slot)
+        .collect();
+    slots.sort();
+    let n = slots.len();
+    for (i, slot) in slots.into_iter().enumerate() {
+        let Some(stack) = inv.take_from_slot(slot, u32::MAX) else {
+            continue;
+        };
+        // Spread round in a ring so they don't all land in a heap
+        let out = Quat::from_rotation_y(TAU * i as f32 / n as f32) * Vec3::X;
+        commands.trigger(SpawnItem {
+            stack,
+            pos: t.translation + Vec3::Y + out * 0.3,
+            velocity: (out + Vec3::Y) * DEATH_SCATTER,
+        });
+    }
+}
+
+fn respawn(
+    time: Res<Time>,
+    spawn_point: Res<SpawnPoint>,
+    homes: Query<&GlobalTransform, With<Home>>,
+    mut player: Query<(Entity, &mut Transform, &mut Health, &mut StatusEffects, &mut CharacterController, &mut Dead), With<Player>>,
+    mut commands: Commands,
+) {
+    let Ok((e, mut t, mut health, mut effects, mut controller, mut dead)) = player.get_single_mut() else {
+        return;
+    };
+    dead.t += time.delta_secs();
+    if dead.t < RESPAWN_SECS {
+        return;
+    }
+
+    let pos = spawn_point.0
+        .or_else(|| homes.iter().next().map(|h| h.transform_point(HOME_SPAWN)))
+        .unwrap_or(HOME_SPAWN);
+    info!("waking up at {:?}", pos);
+    t.translation = pos;
+    health.0 = PLAYER_HEALTH;
+    effects.0.clear();
+    controller.stop();
+    commands.entity(e).remove::<Dead>();
+}
+
+/// Fade to black while dead, and back once alive again
+fn fade(
+    time: Res<Time>,
+    dead: Query<(), (With<Player>, With<Dead>)>,
+    mut fades: Query<&mut BackgroundColor, With<Fade>>,
+) {
+    let step = time.delta_secs() / FADE_SECS;
+    let target = if dead.is_empty() { 0.0 } else { 1.0 };
+    for mut bg in fades.iter_mut() {
+        let alpha = bg.0.alpha();
+        let next = if alpha < target { (alpha + step).min(target) } else { (alpha - step).max(target) };
+        if next != alpha {
+            bg.0.set_alpha(next);
+        }
+    }
+}
+
+fn sleep_in_bed(
+    actions: Res<ActionState>,
+    ray_target: Res<RaycastTarget>,
+    beds: Query<&GlobalTransform, With<Bed>>,
+    parent_q: Query<&Parent>,
+    mut spawn_point: ResMut<SpawnPoint>,
+) {
+    if !actions.just_pressed(Action::Interact) {
+        return;
+    }
+    let Some(mesh) = ray_target.mesh else {
+        return;
+    };
+    let bed = std::iter::once(mesh)
+        .chain(parent_q.iter_ancestors(mesh))
+        .find_map(|e| beds.get(e).ok());
+    if let Some(bed) = bed {
+        info!("slept, will wake up here");
+        spawn_point.0 = Some(bed.translation() + Vec3::Y);
+    }
+}
+
+fn show_health(
+    player: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut texts: Query<&mut Text, With<HealthText>>,
+) {
+    let Ok(health) = player.get_single() else {
+        return;
+    };
+    for mut text in texts.iter_mut() {
+        **text = format!("Health {:.0}", health.0.max(0.0));
+    }
+}